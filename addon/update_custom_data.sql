@@ -1,3 +1,10 @@
+-- DEPRECATED: use `anki_srs_kai_cli update-custom-data` instead, which counts the streaks
+-- exactly like the custom scheduler. This script only matches the default `easeReward` settings.
+-- It ignores `hardBehaviour` and `streakCredit`, sets the count to 0 instead of removing it after
+-- pressing Again, and does not count reviews of review cards rescheduled by a filtered deck.
+--
+-- Hard (ease=2) is ignored, which matches the default `hardBehaviour` of "preserve" in
+-- `easeReward`
 UPDATE cards
 -- Merge our customData changes into the existing JSON object
 SET data = json_patch(
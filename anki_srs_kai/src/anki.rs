@@ -17,9 +17,7 @@ pub struct FsrsMemoryState {
     // Anki encodes this as f32 but since all numbers are f64 in JavaScript, we
     // use f64 instead to avoid loss of precision when we cross the Rust and
     // JavaScript boundary
-    #[allow(dead_code)]
     pub stability: f64,
-    #[allow(dead_code)]
     pub difficulty: f64,
 }
 
@@ -33,11 +31,9 @@ pub struct NewState {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LearnState {
-    #[allow(dead_code)]
     pub remaining_steps: u32,
     #[allow(dead_code)]
     pub scheduled_secs: u32,
-    #[allow(dead_code)]
     pub elapsed_secs: u32,
     #[allow(dead_code)]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -53,11 +49,8 @@ pub struct ReviewState {
     // use f64 instead to avoid loss of precision when we cross the Rust and
     // JavaScript boundary
     pub ease_factor: f64,
-    #[allow(dead_code)]
     pub lapses: u32,
-    #[allow(dead_code)]
     pub leeched: bool,
-    #[allow(dead_code)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub memory_state: Option<FsrsMemoryState>,
 }
@@ -67,7 +60,6 @@ pub struct ReviewState {
 pub struct RelearnState {
     #[allow(dead_code)]
     pub learning: LearnState,
-    #[allow(dead_code)]
     pub review: ReviewState,
 }
 
@@ -76,10 +68,8 @@ pub struct RelearnState {
 pub enum NormalState {
     #[allow(dead_code)]
     New(NewState),
-    #[allow(dead_code)]
     Learning(LearnState),
     Review(ReviewState),
-    #[allow(dead_code)]
     Relearning(RelearnState),
 }
 
@@ -156,6 +146,17 @@ pub mod javascript {
 
         #[wasm_bindgen(method, getter, js_name = review)]
         pub fn review(this: &NormalState) -> ReviewState;
+
+        #[wasm_bindgen(method, getter, js_name = relearning)]
+        pub fn relearning(this: &NormalState) -> RelearnState;
+    }
+
+    #[wasm_bindgen]
+    extern "C" {
+        pub type RelearnState;
+
+        #[wasm_bindgen(method, getter, js_name = review)]
+        pub fn review(this: &RelearnState) -> ReviewState;
     }
 
     #[wasm_bindgen]
@@ -192,6 +193,9 @@ pub mod javascript {
         #[wasm_bindgen(thread_local_v2, js_name = states)]
         pub static STATES: SchedulingStates;
 
+        #[wasm_bindgen(method, getter, js_name = again)]
+        pub fn again(this: &SchedulingStates) -> SchedulingState;
+
         #[wasm_bindgen(method, getter, js_name = hard)]
         pub fn hard(this: &SchedulingStates) -> SchedulingState;
 
@@ -259,5 +263,17 @@ pub mod javascript {
 
         #[wasm_bindgen(method, setter, js_name = c)]
         pub fn set_c(this: &CustomDataState, c: Option<u32>);
+
+        #[wasm_bindgen(method, getter, js_name = f)]
+        pub fn f(this: &CustomDataState) -> Option<u32>;
+
+        #[wasm_bindgen(method, setter, js_name = f)]
+        pub fn set_f(this: &CustomDataState, f: Option<u32>);
+
+        #[wasm_bindgen(method, getter, js_name = p)]
+        pub fn p(this: &CustomDataState) -> Option<u32>;
+
+        #[wasm_bindgen(method, setter, js_name = p)]
+        pub fn set_p(this: &CustomDataState, p: Option<u32>);
     }
 }
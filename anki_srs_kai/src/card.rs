@@ -0,0 +1,76 @@
+use crate::fsrs::MemoryState;
+
+/// A review card that is about to be answered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReviewCard {
+    /// The name of the deck of the card, which is passed to the multiplier
+    /// functions.
+    pub deck_name: String,
+    pub scheduled_days: u32,
+    pub elapsed_days: u32,
+    pub ease_factor: f64,
+    /// The number of consecutive successful reviews, stored under `c` in the
+    /// custom data of the card.
+    pub consecutive_successful_reviews: Option<u32>,
+    /// The number of consecutive failed reviews, stored under `f` in the custom
+    /// data of the card.
+    pub consecutive_failed_reviews: Option<u32>,
+    pub lapses: u32,
+    /// Whether Anki marked the card as a leech.
+    pub leeched: bool,
+    /// The FSRS memory state of the card, if FSRS is enabled in Anki.
+    pub memory_state: Option<MemoryState>,
+}
+
+/// A learning card that is about to graduate to review.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LearningCard {
+    pub remaining_steps: u32,
+    /// The number of seconds since the card was last answered.
+    pub elapsed_secs: u32,
+}
+
+/// A relearning card that is about to graduate back to review.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelearningCard {
+    /// The name of the deck of the card, which is passed to the lapse
+    /// multiplier function.
+    pub deck_name: String,
+    pub ease_factor: f64,
+    /// The interval of the card before it lapsed.
+    pub previous_interval: u32,
+    pub lapses: u32,
+    /// Whether Anki marked the card as a leech.
+    pub leeched: bool,
+    /// The FSRS memory state of the card, if FSRS is enabled in Anki.
+    pub memory_state: Option<MemoryState>,
+}
+
+/// The state of the card after pressing one of the answer buttons. Any value
+/// that is `None` is left to Anki's native scheduler, except for the number of
+/// consecutive successful and failed reviews, where `None` removes it from the
+/// custom data of the card.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NextCardState {
+    pub scheduled_days: Option<u32>,
+    pub ease_factor: Option<f64>,
+    pub consecutive_successful_reviews: Option<u32>,
+    pub consecutive_failed_reviews: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NextCardStates {
+    pub again: NextCardState,
+    pub hard: NextCardState,
+    pub good: NextCardState,
+    pub easy: NextCardState,
+}
+
+/// The answer button pressed when reviewing a card.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Answer {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
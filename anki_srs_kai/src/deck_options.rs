@@ -0,0 +1,858 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use regex_lite::Regex;
+use serde::Deserialize;
+
+use crate::card::{
+    Answer, LearningCard, NextCardState, NextCardStates, RelearningCard, ReviewCard,
+};
+use crate::ease_penalty::EasePenalty;
+use crate::ease_reward::{next_consecutive_successful_reviews, EaseReward};
+use crate::lapse::Lapse;
+use crate::learning::Learning;
+use crate::leech_policy::LeechPolicy;
+use crate::load_balancer::LoadBalancer;
+use crate::multiplier::MultiplierError;
+use crate::scheduler::{NextState, Scheduler};
+use crate::validation::Validator;
+
+const DECK_SEPARATOR: &str = "::";
+const GLOBAL_SETTINGS: &str = "Global Settings";
+/// Anki increases the ease factor by 15% when pressing Easy before the ease
+/// reward is applied.
+const EASE_FACTOR_EASY_DELTA: f64 = 0.15;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeckOptions {
+    ease_reward: EaseReward,
+    #[serde(default)]
+    ease_penalty: Option<EasePenalty>,
+    scheduler: Scheduler,
+    #[serde(default)]
+    lapse: Option<Lapse>,
+    #[serde(default)]
+    learning: Option<Learning>,
+    #[serde(default)]
+    leech_policy: Option<LeechPolicy>,
+}
+
+impl DeckOptions {
+    pub const FIELDS: &'static [&'static str] = &[
+        "easeReward",
+        "easePenalty",
+        "scheduler",
+        "lapse",
+        "learning",
+        "leechPolicy",
+    ];
+
+    pub fn new(ease_reward: EaseReward, scheduler: Scheduler) -> Self {
+        Self {
+            ease_reward,
+            ease_penalty: None,
+            scheduler,
+            lapse: None,
+            learning: None,
+            leech_policy: None,
+        }
+    }
+
+    /// Penalize the ease factor when pressing Again or Hard, instead of leaving
+    /// it to Anki.
+    pub fn with_ease_penalty(mut self, ease_penalty: EasePenalty) -> Self {
+        self.ease_penalty = Some(ease_penalty);
+        self
+    }
+
+    /// Calculate the interval of relearning cards graduating back to review,
+    /// instead of leaving it to Anki.
+    pub fn with_lapse(mut self, lapse: Lapse) -> Self {
+        self.lapse = Some(lapse);
+        self
+    }
+
+    /// Calculate the interval of learning cards graduating to review, instead
+    /// of leaving it to Anki.
+    pub fn with_learning(mut self, learning: Learning) -> Self {
+        self.learning = Some(learning);
+        self
+    }
+
+    /// Keep leeches and cards that lapse often from growing back to long
+    /// intervals.
+    pub fn with_leech_policy(mut self, leech_policy: LeechPolicy) -> Self {
+        self.leech_policy = Some(leech_policy);
+        self
+    }
+
+    pub fn ease_reward(&self) -> &EaseReward {
+        &self.ease_reward
+    }
+
+    pub fn ease_penalty(&self) -> Option<&EasePenalty> {
+        self.ease_penalty.as_ref()
+    }
+
+    pub fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    pub fn lapse(&self) -> Option<&Lapse> {
+        self.lapse.as_ref()
+    }
+
+    pub fn learning(&self) -> Option<&Learning> {
+        self.learning.as_ref()
+    }
+
+    pub fn leech_policy(&self) -> Option<&LeechPolicy> {
+        self.leech_policy.as_ref()
+    }
+
+    pub fn validate(&self, deck_name: &str, validator: &mut Validator) {
+        validator.add_invalid_fields(deck_name, "easeReward", self.ease_reward.validate());
+        if let Some(ease_penalty) = &self.ease_penalty {
+            validator.add_invalid_fields(deck_name, "easePenalty", ease_penalty.validate());
+        }
+        validator.add_invalid_fields(deck_name, "scheduler", self.scheduler.validate());
+        if let Some(lapse) = &self.lapse {
+            validator.add_invalid_fields(deck_name, "lapse", lapse.validate());
+        }
+        if let Some(learning) = &self.learning {
+            validator.add_invalid_fields(deck_name, "learning", learning.validate());
+        }
+        if let Some(leech_policy) = &self.leech_policy {
+            validator.add_invalid_fields(deck_name, "leechPolicy", leech_policy.validate());
+        }
+    }
+
+    /// Calculate the state of a review card after pressing each of the answer
+    /// buttons. The intervals are spread across days by the load balancer, if
+    /// one is given.
+    pub fn next_card_states(
+        &self,
+        fuzz_seed: u64,
+        card: &ReviewCard,
+        load_balancer: Option<&LoadBalancer>,
+    ) -> Result<NextCardStates, MultiplierError> {
+        let next_states = self.scheduler.next_states(
+            fuzz_seed,
+            card,
+            self.leech_policy.as_ref(),
+            load_balancer,
+        )?;
+
+        let streak_credit = self
+            .ease_reward
+            .streak_credit(card.scheduled_days, card.elapsed_days);
+        let next_consecutive_successful_reviews = |answer| {
+            next_consecutive_successful_reviews(
+                answer,
+                card.consecutive_successful_reviews,
+                streak_credit,
+                self.ease_reward.hard_behaviour(),
+            )
+        };
+        let number_of_successful_reviews = card
+            .consecutive_successful_reviews
+            .unwrap_or(0)
+            .saturating_add(streak_credit);
+        let suspends_ease_reward = self
+            .leech_policy
+            .as_ref()
+            .is_some_and(|leech_policy| leech_policy.suspends_ease_reward(card.lapses));
+        let successful_review = |scheduled_days, ease_factor| NextCardState {
+            scheduled_days,
+            ease_factor: Some(if suspends_ease_reward {
+                ease_factor
+            } else {
+                self.ease_reward
+                    .calculate_new_ease_factor(number_of_successful_reviews, ease_factor)
+            }),
+            consecutive_successful_reviews: next_consecutive_successful_reviews(Answer::Good),
+            consecutive_failed_reviews: None,
+        };
+
+        // The number of consecutive failed reviews is only kept track of when
+        // the ease penalty is enabled
+        let number_of_failed_reviews = card
+            .consecutive_failed_reviews
+            .map_or(1, |f| f.saturating_add(1));
+        let failed_review =
+            |scheduled_days, answer, consecutive_successful_reviews| NextCardState {
+                scheduled_days,
+                ease_factor: self.ease_penalty.as_ref().map(|ease_penalty| {
+                    ease_penalty.calculate_new_ease_factor(
+                        answer,
+                        number_of_failed_reviews,
+                        card.ease_factor,
+                    )
+                }),
+                consecutive_successful_reviews,
+                consecutive_failed_reviews: self
+                    .ease_penalty
+                    .as_ref()
+                    .map(|_| number_of_failed_reviews),
+            };
+
+        Ok(NextCardStates {
+            again: failed_review(
+                None,
+                Answer::Again,
+                next_consecutive_successful_reviews(Answer::Again),
+            ),
+            hard: failed_review(
+                next_states.hard_interval,
+                Answer::Hard,
+                next_consecutive_successful_reviews(Answer::Hard),
+            ),
+            good: successful_review(next_states.good_interval, card.ease_factor),
+            easy: successful_review(
+                next_states.easy_interval,
+                card.ease_factor + EASE_FACTOR_EASY_DELTA,
+            ),
+        })
+    }
+
+    /// Calculate the intervals of a relearning card graduating back to review
+    /// after pressing Good or Easy, or `None` if the lapse section is not
+    /// configured.
+    pub fn next_relearning_states(
+        &self,
+        fuzz_seed: u64,
+        card: &RelearningCard,
+        load_balancer: Option<&LoadBalancer>,
+    ) -> Result<Option<NextState>, MultiplierError> {
+        self.lapse
+            .as_ref()
+            .map(|lapse| {
+                lapse.next_states(
+                    &self.scheduler,
+                    fuzz_seed,
+                    card,
+                    self.leech_policy.as_ref(),
+                    load_balancer,
+                )
+            })
+            .transpose()
+    }
+
+    /// Calculate the intervals of a learning card graduating to review after
+    /// pressing Good on the last learning step or Easy, or `None` if the
+    /// learning section is not configured.
+    pub fn next_learning_states(
+        &self,
+        fuzz_seed: u64,
+        card: &LearningCard,
+        load_balancer: Option<&LoadBalancer>,
+    ) -> Result<Option<NextState>, MultiplierError> {
+        self.learning
+            .as_ref()
+            .map(|learning| {
+                learning.next_states(
+                    &self.scheduler,
+                    fuzz_seed,
+                    card.remaining_steps,
+                    card.elapsed_secs,
+                    load_balancer,
+                )
+            })
+            .transpose()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidPatternError {
+    pattern: String,
+    reason: String,
+}
+
+impl Display for InvalidPatternError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid deck name pattern `{}`: {}",
+            self.pattern, self.reason
+        )
+    }
+}
+
+/// A deck name in the deck options can either be the exact name of a deck, a
+/// glob pattern such as `Japanese::*`, or a regular expression surrounded by
+/// forward slashes such as `/^Lang::.*::Listening$/`.
+enum DeckNamePattern {
+    /// `*` matches any sequence of characters, including `::`, and `?` matches
+    /// exactly one character.
+    Glob(String),
+    Regex(Regex),
+}
+
+impl DeckNamePattern {
+    fn parse(deck_name: &str) -> Result<Option<Self>, InvalidPatternError> {
+        if let Some(regex) = deck_name
+            .strip_prefix('/')
+            .and_then(|deck_name| deck_name.strip_suffix('/'))
+        {
+            return Regex::new(regex)
+                .map(|regex| Some(DeckNamePattern::Regex(regex)))
+                .map_err(|e| InvalidPatternError {
+                    pattern: deck_name.to_string(),
+                    reason: e.to_string(),
+                });
+        }
+
+        if deck_name.contains(['*', '?']) {
+            Ok(Some(DeckNamePattern::Glob(deck_name.to_string())))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn is_match(&self, deck_name: &str) -> bool {
+        match self {
+            DeckNamePattern::Glob(glob) => glob_matches(glob, deck_name),
+            DeckNamePattern::Regex(regex) => regex.is_match(deck_name),
+        }
+    }
+
+    /// Glob patterns are more specific than regular expressions. Between two
+    /// glob patterns, the one with more literal characters is more specific.
+    fn cmp_specificity(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (DeckNamePattern::Glob(glob), DeckNamePattern::Glob(other_glob)) => {
+                literal_characters(glob).cmp(&literal_characters(other_glob))
+            }
+            (DeckNamePattern::Glob(_), DeckNamePattern::Regex(_)) => Ordering::Greater,
+            (DeckNamePattern::Regex(_), DeckNamePattern::Glob(_)) => Ordering::Less,
+            (DeckNamePattern::Regex(_), DeckNamePattern::Regex(_)) => Ordering::Equal,
+        }
+    }
+}
+
+fn literal_characters(glob: &str) -> usize {
+    glob.chars().filter(|c| !matches!(c, '*' | '?')).count()
+}
+
+fn glob_matches(glob: &str, deck_name: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<char>>();
+    let deck_name = deck_name.chars().collect::<Vec<char>>();
+
+    let (mut g, mut d) = (0, 0);
+    // The position of the last `*` in the glob and the position in the deck
+    // name it is currently matched up to, so that we can backtrack
+    let mut backtrack: Option<(usize, usize)> = None;
+    while d < deck_name.len() {
+        match glob.get(g) {
+            Some('*') => {
+                backtrack = Some((g, d));
+                g += 1;
+            }
+            Some('?') => {
+                g += 1;
+                d += 1;
+            }
+            Some(c) if *c == deck_name[d] => {
+                g += 1;
+                d += 1;
+            }
+            _ => match backtrack {
+                None => return false,
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    g = star + 1;
+                    d = matched + 1;
+                }
+            },
+        }
+    }
+
+    glob[g..].iter().all(|c| *c == '*')
+}
+
+/// The options of every configured deck name, with the glob patterns and
+/// regular expressions among them compiled once when they are added.
+pub struct DeckNameToDeckOptions<T> {
+    deck_name_to_deck_options: HashMap<String, T>,
+    patterns: Vec<(String, DeckNamePattern)>,
+}
+
+impl<T> Default for DeckNameToDeckOptions<T> {
+    fn default() -> Self {
+        Self {
+            deck_name_to_deck_options: HashMap::new(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+impl<T> DeckNameToDeckOptions<T> {
+    /// Add the options of a deck name, which can also be a glob pattern or a
+    /// regular expression. A deck name that is an invalid regular expression is
+    /// an error and is left out.
+    pub fn insert(
+        &mut self,
+        deck_name: String,
+        deck_options: T,
+    ) -> Result<(), InvalidPatternError> {
+        let pattern = DeckNamePattern::parse(&deck_name)?;
+        if let Some(pattern) = pattern {
+            if !self.deck_name_to_deck_options.contains_key(&deck_name) {
+                self.patterns.push((deck_name.clone(), pattern));
+            }
+        }
+        self.deck_name_to_deck_options
+            .insert(deck_name, deck_options);
+        Ok(())
+    }
+
+    /// Return the options of the deck, resolved in the following order
+    ///
+    /// 1. The exact name of the deck.
+    /// 2. The most specific glob pattern or regular expression matching the
+    ///    name of the deck. Ties are broken by the pattern that comes first
+    ///    alphabetically.
+    /// 3. The exact name of the closest parent deck. For example, the deck
+    ///    `Japanese::Vocab::N3` will look for `Japanese::Vocab`, and then
+    ///    `Japanese`. A pattern matching the deck is always preferred over a
+    ///    parent deck, so `Japanese::*` is used before `Japanese::Vocab`.
+    /// 4. `Global Settings`.
+    pub fn find(&self, deck_name: &str) -> Option<&T> {
+        if let Some(deck_options) = self.deck_name_to_deck_options.get(deck_name) {
+            return Some(deck_options);
+        }
+
+        let mut best_match: Option<&(String, DeckNamePattern)> = None;
+        for candidate in &self.patterns {
+            let (pattern, parsed_pattern) = candidate;
+            if !parsed_pattern.is_match(deck_name) {
+                continue;
+            }
+            let is_better_match = match best_match {
+                None => true,
+                Some((best_pattern, best_parsed_pattern)) => {
+                    match parsed_pattern.cmp_specificity(best_parsed_pattern) {
+                        Ordering::Greater => true,
+                        Ordering::Less => false,
+                        Ordering::Equal => pattern < best_pattern,
+                    }
+                }
+            };
+            if is_better_match {
+                best_match = Some(candidate);
+            }
+        }
+        if let Some((pattern, _)) = best_match {
+            return self.deck_name_to_deck_options.get(pattern);
+        }
+
+        let mut deck_name = deck_name;
+        while let Some((parent_deck_name, _)) = deck_name.rsplit_once(DECK_SEPARATOR) {
+            if let Some(deck_options) = self.deck_name_to_deck_options.get(parent_deck_name) {
+                return Some(deck_options);
+            }
+            deck_name = parent_deck_name;
+        }
+
+        self.deck_name_to_deck_options.get(GLOBAL_SETTINGS)
+    }
+}
+
+impl<T> TryFrom<HashMap<String, T>> for DeckNameToDeckOptions<T> {
+    type Error = InvalidPatternError;
+
+    fn try_from(deck_name_to_deck_options: HashMap<String, T>) -> Result<Self, Self::Error> {
+        let mut result = Self::default();
+        for (deck_name, deck_options) in deck_name_to_deck_options {
+            result.insert(deck_name, deck_options)?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::{NextCardState, NextCardStates, ReviewCard};
+    use crate::deck_options::{DeckNameToDeckOptions, DeckOptions};
+    use crate::ease_penalty::EasePenalty;
+    use crate::ease_reward::EaseReward;
+    use crate::leech_policy::LeechPolicy;
+    use crate::multiplier::constant_multiplier;
+    use crate::scheduler::Scheduler;
+    use crate::validation::deserialized_fields;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn deck_name_to_deck_options(deck_names: &[&str]) -> DeckNameToDeckOptions<String> {
+        let mut deck_name_to_deck_options = DeckNameToDeckOptions::default();
+        for deck_name in deck_names {
+            deck_name_to_deck_options
+                .insert(deck_name.to_string(), deck_name.to_string())
+                .unwrap();
+        }
+        deck_name_to_deck_options
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn exact_deck_name_is_used() {
+        let under_test = deck_name_to_deck_options(&[
+            "Japanese",
+            "Japanese::Vocab",
+            "Japanese::*",
+            "Global Settings",
+        ]);
+
+        let result = under_test.find("Japanese::Vocab");
+
+        assert_eq!(result.map(String::as_str), Some("Japanese::Vocab"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn closest_parent_deck_is_used() {
+        let under_test =
+            deck_name_to_deck_options(&["Japanese", "Japanese::Vocab", "Global Settings"]);
+
+        let result = under_test.find("Japanese::Vocab::N3::Verbs");
+
+        assert_eq!(result.map(String::as_str), Some("Japanese::Vocab"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn global_settings_is_used_if_no_parent_deck_is_configured() {
+        let under_test = deck_name_to_deck_options(&["Japanese", "Global Settings"]);
+
+        let result = under_test.find("Korean::Vocab");
+
+        assert_eq!(result.map(String::as_str), Some("Global Settings"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn no_deck_options_if_global_settings_is_not_configured() {
+        let under_test = deck_name_to_deck_options(&["Japanese"]);
+
+        let result = under_test.find("Korean::Vocab");
+
+        assert_eq!(result, None);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn parent_deck_must_match_a_whole_deck_name() {
+        let under_test = deck_name_to_deck_options(&["Japan", "Global Settings"]);
+
+        let result = under_test.find("Japanese::Vocab");
+
+        assert_eq!(result.map(String::as_str), Some("Global Settings"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn glob_pattern_is_used_before_parent_deck() {
+        let under_test =
+            deck_name_to_deck_options(&["Japanese", "Japanese::*::N?", "Global Settings"]);
+
+        let result = under_test.find("Japanese::Vocab::N3");
+
+        assert_eq!(result.map(String::as_str), Some("Japanese::*::N?"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn regex_pattern_is_used_before_parent_deck() {
+        let under_test =
+            deck_name_to_deck_options(&["Lang", "/^Lang::.*::Listening$/", "Global Settings"]);
+
+        let result = under_test.find("Lang::JP::Listening");
+
+        assert_eq!(result.map(String::as_str), Some("/^Lang::.*::Listening$/"));
+
+        let result = under_test.find("Lang::JP::Reading");
+
+        assert_eq!(result.map(String::as_str), Some("Lang"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn regex_pattern_is_used() {
+        let under_test = deck_name_to_deck_options(&["/^Lang::.*::Listening$/", "Global Settings"]);
+
+        let result = under_test.find("Lang::Korean::Listening");
+
+        assert_eq!(result.map(String::as_str), Some("/^Lang::.*::Listening$/"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn glob_pattern_with_more_literal_characters_is_used() {
+        let under_test = deck_name_to_deck_options(&[
+            "*",
+            "Japanese::*",
+            "Japanese::Vocab::*",
+            "/^Japanese::Vocab::N3$/",
+        ]);
+
+        let result = under_test.find("Japanese::Vocab::N3");
+
+        assert_eq!(result.map(String::as_str), Some("Japanese::Vocab::*"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn equally_specific_patterns_are_resolved_alphabetically() {
+        let under_test = deck_name_to_deck_options(&["Ja*::N?", "J*::N3", "/N3/", "/N/"]);
+
+        let result = under_test.find("Japanese::N3");
+
+        assert_eq!(result.map(String::as_str), Some("J*::N3"));
+
+        let under_test = deck_name_to_deck_options(&["/N3/", "/N/"]);
+
+        let result = under_test.find("Japanese::N3");
+
+        assert_eq!(result.map(String::as_str), Some("/N/"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn glob_pattern_must_match_the_whole_deck_name() {
+        let under_test = deck_name_to_deck_options(&["Japanese::*::Kanji", "Global Settings"]);
+
+        let result = under_test.find("Japanese::Vocab::Kanji::Radicals");
+
+        assert_eq!(result.map(String::as_str), Some("Global Settings"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn invalid_regex_pattern_is_an_error() {
+        let mut under_test = DeckNameToDeckOptions::default();
+
+        let result = under_test.insert("/(/".to_string(), ());
+
+        assert!(result.is_err());
+        assert_eq!(under_test.find("Japanese"), None);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn next_card_states_of_review_card() {
+        let under_test = DeckOptions::new(
+            EaseReward::new(2, 0.05, 0.05, 1.30, 2.50),
+            Scheduler::new(
+                false,
+                36500,
+                1.0,
+                constant_multiplier(2.0),
+                constant_multiplier(3.0),
+                constant_multiplier(4.0),
+            ),
+        );
+
+        let result = under_test.next_card_states(
+            123,
+            &ReviewCard {
+                deck_name: "Japanese".to_string(),
+                scheduled_days: 50,
+                elapsed_days: 50,
+                ease_factor: 2.0,
+                consecutive_successful_reviews: Some(2),
+                consecutive_failed_reviews: None,
+                lapses: 0,
+                leeched: false,
+                memory_state: None,
+            },
+            None,
+        );
+
+        assert_eq!(
+            result,
+            Ok(NextCardStates {
+                again: NextCardState {
+                    scheduled_days: None,
+                    ease_factor: None,
+                    consecutive_successful_reviews: None,
+                    consecutive_failed_reviews: None,
+                },
+                hard: NextCardState {
+                    scheduled_days: Some(100),
+                    ease_factor: None,
+                    consecutive_successful_reviews: Some(2),
+                    consecutive_failed_reviews: None,
+                },
+                good: NextCardState {
+                    scheduled_days: Some(150),
+                    ease_factor: Some(2.1),
+                    consecutive_successful_reviews: Some(3),
+                    consecutive_failed_reviews: None,
+                },
+                easy: NextCardState {
+                    scheduled_days: Some(200),
+                    ease_factor: Some(2.25),
+                    consecutive_successful_reviews: Some(3),
+                    consecutive_failed_reviews: None,
+                },
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn next_card_states_of_review_card_with_ease_penalty() {
+        let under_test = DeckOptions::new(
+            EaseReward::new(2, 0.05, 0.05, 1.30, 2.50),
+            Scheduler::new(
+                false,
+                36500,
+                1.0,
+                constant_multiplier(2.0),
+                constant_multiplier(3.0),
+                constant_multiplier(4.0),
+            ),
+        )
+        .with_ease_penalty(EasePenalty::new(0.2, 0.15, 2, 0.1, 0.05, 1.30));
+
+        let result = under_test.next_card_states(
+            123,
+            &ReviewCard {
+                deck_name: "Japanese".to_string(),
+                scheduled_days: 50,
+                elapsed_days: 50,
+                ease_factor: 2.0,
+                consecutive_successful_reviews: Some(2),
+                consecutive_failed_reviews: Some(1),
+                lapses: 0,
+                leeched: false,
+                memory_state: None,
+            },
+            None,
+        );
+
+        assert_eq!(
+            result,
+            Ok(NextCardStates {
+                again: NextCardState {
+                    scheduled_days: None,
+                    ease_factor: Some(1.7),
+                    consecutive_successful_reviews: None,
+                    consecutive_failed_reviews: Some(2),
+                },
+                hard: NextCardState {
+                    scheduled_days: Some(100),
+                    ease_factor: Some(1.75),
+                    consecutive_successful_reviews: Some(2),
+                    consecutive_failed_reviews: Some(2),
+                },
+                good: NextCardState {
+                    scheduled_days: Some(150),
+                    ease_factor: Some(2.1),
+                    consecutive_successful_reviews: Some(3),
+                    consecutive_failed_reviews: None,
+                },
+                easy: NextCardState {
+                    scheduled_days: Some(200),
+                    ease_factor: Some(2.25),
+                    consecutive_successful_reviews: Some(3),
+                    consecutive_failed_reviews: None,
+                },
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn consecutive_failed_reviews_saturate_instead_of_overflowing() {
+        let under_test = DeckOptions::new(
+            EaseReward::new(2, 0.05, 0.05, 1.30, 2.50),
+            Scheduler::new(
+                false,
+                36500,
+                1.0,
+                constant_multiplier(2.0),
+                constant_multiplier(3.0),
+                constant_multiplier(4.0),
+            ),
+        )
+        .with_ease_penalty(EasePenalty::new(0.2, 0.15, 2, 0.1, 0.05, 1.30));
+
+        let result = under_test.next_card_states(
+            123,
+            &ReviewCard {
+                deck_name: "Japanese".to_string(),
+                scheduled_days: 50,
+                elapsed_days: 50,
+                ease_factor: 2.0,
+                consecutive_successful_reviews: None,
+                consecutive_failed_reviews: Some(u32::MAX),
+                lapses: 0,
+                leeched: false,
+                memory_state: None,
+            },
+            None,
+        );
+
+        assert_eq!(
+            result.map(|result| (
+                result.again.consecutive_failed_reviews,
+                result.again.ease_factor
+            )),
+            Ok((Some(u32::MAX), Some(1.3)))
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn next_card_states_of_leech_with_leech_policy() {
+        let under_test = DeckOptions::new(
+            EaseReward::new(2, 0.05, 0.05, 1.30, 2.50),
+            Scheduler::new(
+                false,
+                36500,
+                1.0,
+                constant_multiplier(2.0),
+                constant_multiplier(3.0),
+                constant_multiplier(4.0),
+            ),
+        )
+        .with_leech_policy(LeechPolicy::new(Some(8), Some(60), Some(0.9), Some(4)));
+
+        let result = under_test.next_card_states(
+            123,
+            &ReviewCard {
+                deck_name: "Japanese".to_string(),
+                scheduled_days: 50,
+                elapsed_days: 50,
+                ease_factor: 2.0,
+                consecutive_successful_reviews: Some(2),
+                consecutive_failed_reviews: None,
+                lapses: 8,
+                leeched: false,
+                memory_state: None,
+            },
+            None,
+        );
+
+        assert_eq!(
+            result,
+            Ok(NextCardStates {
+                again: NextCardState {
+                    scheduled_days: None,
+                    ease_factor: None,
+                    consecutive_successful_reviews: None,
+                    consecutive_failed_reviews: None,
+                },
+                hard: NextCardState {
+                    scheduled_days: Some(51),
+                    ease_factor: None,
+                    consecutive_successful_reviews: Some(2),
+                    consecutive_failed_reviews: None,
+                },
+                good: NextCardState {
+                    scheduled_days: Some(60),
+                    ease_factor: Some(2.0),
+                    consecutive_successful_reviews: Some(3),
+                    consecutive_failed_reviews: None,
+                },
+                easy: NextCardState {
+                    scheduled_days: Some(60),
+                    ease_factor: Some(2.15),
+                    consecutive_successful_reviews: Some(3),
+                    consecutive_failed_reviews: None,
+                },
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn known_fields_match_deserialized_fields() {
+        assert_eq!(deserialized_fields::<DeckOptions>(), DeckOptions::FIELDS);
+    }
+}
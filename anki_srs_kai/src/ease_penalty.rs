@@ -0,0 +1,233 @@
+use serde::{Deserialize, Deserializer};
+
+use crate::card::Answer;
+use crate::ease_reward::{round_to_places, ABSOLUTE_MAXIMUM_EASE, ABSOLUTE_MINIMUM_EASE};
+use crate::validation::InvalidField;
+
+pub struct EasePenalty {
+    again_ease_penalty: f64,
+    hard_ease_penalty: f64,
+    minimum_consecutive_failed_reviews_required_for_penalty: u32,
+    base_ease_penalty: f64,
+    step_ease_penalty: f64,
+    minimum_ease: f64,
+}
+
+impl<'de> Deserialize<'de> for EasePenalty {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Fields {
+            again_ease_penalty: f64,
+            hard_ease_penalty: f64,
+            minimum_consecutive_failed_reviews_required_for_penalty: u32,
+            base_ease_penalty: f64,
+            step_ease_penalty: f64,
+            minimum_ease: f64,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+
+        Ok(EasePenalty::new(
+            fields.again_ease_penalty,
+            fields.hard_ease_penalty,
+            fields.minimum_consecutive_failed_reviews_required_for_penalty,
+            fields.base_ease_penalty,
+            fields.step_ease_penalty,
+            fields.minimum_ease,
+        ))
+    }
+}
+
+impl EasePenalty {
+    pub const FIELDS: &'static [&'static str] = &[
+        "againEasePenalty",
+        "hardEasePenalty",
+        "minimumConsecutiveFailedReviewsRequiredForPenalty",
+        "baseEasePenalty",
+        "stepEasePenalty",
+        "minimumEase",
+    ];
+
+    pub fn new(
+        again_ease_penalty: f64,
+        hard_ease_penalty: f64,
+        minimum_consecutive_failed_reviews_required_for_penalty: u32,
+        base_ease_penalty: f64,
+        step_ease_penalty: f64,
+        minimum_ease: f64,
+    ) -> Self {
+        Self {
+            again_ease_penalty,
+            hard_ease_penalty,
+            minimum_consecutive_failed_reviews_required_for_penalty,
+            base_ease_penalty,
+            step_ease_penalty,
+            minimum_ease,
+        }
+    }
+
+    pub fn validate(&self) -> Vec<InvalidField> {
+        let mut invalid_fields = Vec::new();
+        for (field, value) in [
+            ("againEasePenalty", self.again_ease_penalty),
+            ("hardEasePenalty", self.hard_ease_penalty),
+            ("baseEasePenalty", self.base_ease_penalty),
+            ("stepEasePenalty", self.step_ease_penalty),
+            ("minimumEase", self.minimum_ease),
+        ] {
+            if !value.is_finite() {
+                invalid_fields.push(InvalidField::new(field, format!("{value} is not a number")));
+            } else if value < 0.0 {
+                invalid_fields.push(InvalidField::new(field, format!("{value} is negative")));
+            }
+        }
+        if self.minimum_ease < ABSOLUTE_MINIMUM_EASE {
+            invalid_fields.push(InvalidField::new(
+                "minimumEase",
+                format!("{} is below {}", self.minimum_ease, ABSOLUTE_MINIMUM_EASE),
+            ));
+        }
+        if self.minimum_ease > ABSOLUTE_MAXIMUM_EASE {
+            invalid_fields.push(InvalidField::new(
+                "minimumEase",
+                format!("{} exceeds {}", self.minimum_ease, ABSOLUTE_MAXIMUM_EASE),
+            ));
+        }
+        invalid_fields
+    }
+
+    fn minimum_ease(&self) -> f64 {
+        self.minimum_ease
+            .clamp(ABSOLUTE_MINIMUM_EASE, ABSOLUTE_MAXIMUM_EASE)
+    }
+
+    /// Calculate the ease factor after pressing Again or Hard, where
+    /// `number_of_failed_reviews` includes the current review. The ease factor
+    /// is unchanged when pressing Good or Easy.
+    pub fn calculate_new_ease_factor(
+        &self,
+        answer: Answer,
+        number_of_failed_reviews: u32,
+        ease_factor: f64,
+    ) -> f64 {
+        let button_ease_penalty = match answer {
+            Answer::Again => self.again_ease_penalty.max(0.0),
+            Answer::Hard => self.hard_ease_penalty.max(0.0),
+            Answer::Good | Answer::Easy => return ease_factor,
+        };
+        // Cards that are already below the floor are not raised to it
+        if ease_factor <= self.minimum_ease() {
+            return ease_factor;
+        }
+
+        let streak_ease_penalty = if self.minimum_consecutive_failed_reviews_required_for_penalty
+            == 0
+            || number_of_failed_reviews
+                < self.minimum_consecutive_failed_reviews_required_for_penalty
+        {
+            0.0
+        } else {
+            self.base_ease_penalty.max(0.0)
+                + (number_of_failed_reviews
+                    - self.minimum_consecutive_failed_reviews_required_for_penalty)
+                    as f64
+                    * self.step_ease_penalty.max(0.0)
+        };
+        // Anki only stores up to the 3rd decimal place for the ease factor
+        round_to_places(
+            (ease_factor - button_ease_penalty - streak_ease_penalty).max(self.minimum_ease()),
+            3,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::Answer;
+    use crate::ease_penalty::EasePenalty;
+    use crate::validation::{deserialized_fields, InvalidField};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn button_ease_penalty_is_subtracted() {
+        let under_test = EasePenalty::new(0.2, 0.15, 0, 0.05, 0.05, 1.30);
+
+        let again = under_test.calculate_new_ease_factor(Answer::Again, 1, 2.5);
+        let hard = under_test.calculate_new_ease_factor(Answer::Hard, 1, 2.5);
+
+        assert_eq!(again, 2.3);
+        assert_eq!(hard, 2.35);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ease_factor_is_unchanged_when_pressing_good_or_easy() {
+        let under_test = EasePenalty::new(0.2, 0.15, 1, 0.05, 0.05, 1.30);
+
+        let good = under_test.calculate_new_ease_factor(Answer::Good, 3, 2.5);
+        let easy = under_test.calculate_new_ease_factor(Answer::Easy, 3, 2.5);
+
+        assert_eq!(good, 2.5);
+        assert_eq!(easy, 2.5);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn no_streak_ease_penalty_if_number_of_failures_is_below_minimum_required() {
+        let under_test = EasePenalty::new(0.2, 0.15, 3, 0.1, 0.05, 1.30);
+
+        let result = under_test.calculate_new_ease_factor(Answer::Again, 2, 2.5);
+
+        assert_eq!(result, 2.3);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn streak_ease_penalty_added() {
+        let under_test = EasePenalty::new(0.2, 0.15, 2, 0.1, 0.05, 1.30);
+
+        let result = under_test.calculate_new_ease_factor(Answer::Hard, 4, 2.5);
+
+        assert_eq!(result, 2.15);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ease_penalty_below_minimum_is_clamped_to_the_minimum() {
+        let under_test = EasePenalty::new(0.2, 0.15, 1, 0.1, 0.05, 1.70);
+
+        let result = under_test.calculate_new_ease_factor(Answer::Again, 3, 2.0);
+
+        assert_eq!(result, 1.7);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ease_factor_already_below_minimum_is_unchanged() {
+        let under_test = EasePenalty::new(0.2, 0.15, 1, 0.1, 0.05, 1.70);
+
+        let result = under_test.calculate_new_ease_factor(Answer::Again, 3, 1.5);
+
+        assert_eq!(result, 1.5);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn every_invalid_field_is_reported() {
+        let under_test = EasePenalty::new(-0.2, 0.15, 1, f64::NAN, 0.05, 1.00);
+
+        let result = under_test.validate();
+
+        assert_eq!(
+            result,
+            vec![
+                InvalidField::new("againEasePenalty", "-0.2 is negative".to_string()),
+                InvalidField::new("baseEasePenalty", "NaN is not a number".to_string()),
+                InvalidField::new("minimumEase", "1 is below 1.3".to_string()),
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn known_fields_match_deserialized_fields() {
+        assert_eq!(deserialized_fields::<EasePenalty>(), EasePenalty::FIELDS);
+    }
+}
@@ -1,16 +1,134 @@
 use serde::{Deserialize, Deserializer};
 
-fn round_to_places(value: f64, decimal_places: u32) -> f64 {
+use crate::card::Answer;
+use crate::validation::InvalidField;
+
+// Anki does not allow the ease factor to go below 130% or above 999%
+pub(crate) const ABSOLUTE_MINIMUM_EASE: f64 = 1.3;
+pub(crate) const ABSOLUTE_MAXIMUM_EASE: f64 = 9.99;
+
+pub(crate) fn round_to_places(value: f64, decimal_places: u32) -> f64 {
     let factor = 10_f64.powi(decimal_places as i32);
     (value * factor).round() / factor
 }
 
+/// Moves the ease factor a fraction of the way toward a target ease factor on
+/// every successful review, before the ease reward is applied.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EaseGravity {
+    target_ease: f64,
+    strength: f64,
+}
+
+impl EaseGravity {
+    pub const FIELDS: &'static [&'static str] = &["targetEase", "strength"];
+
+    pub fn new(target_ease: f64, strength: f64) -> Self {
+        Self {
+            target_ease,
+            strength,
+        }
+    }
+
+    fn strength(&self) -> f64 {
+        self.strength.clamp(0.0, 1.0)
+    }
+}
+
+/// How much a successful review adds to the streak of consecutive successful
+/// reviews, depending on the interval of the card and how overdue it is.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StreakCredit {
+    minimum_interval: u32,
+    overdue_credit: f64,
+}
+
+impl StreakCredit {
+    pub const FIELDS: &'static [&'static str] = &["minimumInterval", "overdueCredit"];
+
+    pub fn new(minimum_interval: u32, overdue_credit: f64) -> Self {
+        Self {
+            minimum_interval,
+            overdue_credit,
+        }
+    }
+
+    /// Return the credit of a successful review. Reviews of cards with an
+    /// interval below the minimum interval earn no credit, and every other
+    /// review earns 1 plus the overdue credit for each multiple of the interval
+    /// that the card is overdue, rounded down.
+    pub fn credit(&self, scheduled_days: u32, elapsed_days: u32) -> u32 {
+        if scheduled_days < self.minimum_interval {
+            return 0;
+        }
+        let days_late = elapsed_days.saturating_sub(scheduled_days) as f64;
+        let overdue_credit =
+            self.overdue_credit.max(0.0) * days_late / (scheduled_days as f64).max(1.0);
+        // Very overdue cards or a huge overdue credit saturate instead of
+        // overflowing
+        (overdue_credit.floor() as u32).saturating_add(1)
+    }
+}
+
+/// How pressing Hard affects the streak of consecutive successful reviews.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum HardBehaviour {
+    /// Leave the streak as is.
+    #[default]
+    Preserve,
+    /// Reset the streak back to 0, the same as pressing Again.
+    Reset,
+    /// Lower the streak by 1.
+    Decrement,
+    /// Add to the streak, the same as pressing Good or Easy.
+    CountAsSuccess,
+}
+
+/// Return the number of consecutive successful reviews after pressing the
+/// answer button, where `streak_credit` is the credit of a successful review.
+/// `None` removes it from the custom data of the card.
+pub fn next_consecutive_successful_reviews(
+    answer: Answer,
+    consecutive_successful_reviews: Option<u32>,
+    streak_credit: u32,
+    hard_behaviour: HardBehaviour,
+) -> Option<u32> {
+    let successful_review = || {
+        // A successful review without any credit leaves the streak as is
+        if streak_credit == 0 {
+            consecutive_successful_reviews
+        } else {
+            Some(
+                consecutive_successful_reviews
+                    .unwrap_or(0)
+                    .saturating_add(streak_credit),
+            )
+        }
+    };
+    match (answer, hard_behaviour) {
+        (Answer::Again, _) | (Answer::Hard, HardBehaviour::Reset) => None,
+        (Answer::Hard, HardBehaviour::Preserve) => consecutive_successful_reviews,
+        (Answer::Hard, HardBehaviour::Decrement) => consecutive_successful_reviews
+            .map(|c| c.saturating_sub(1))
+            .filter(|&c| c > 0),
+        (Answer::Hard, HardBehaviour::CountAsSuccess) | (Answer::Good | Answer::Easy, _) => {
+            successful_review()
+        }
+    }
+}
+
 pub struct EaseReward {
     minimum_consecutive_successful_reviews_required_for_reward: u32,
     base_ease_reward: f64,
     step_ease_reward: f64,
     minimum_ease: f64,
     maximum_ease: f64,
+    ease_gravity: Option<EaseGravity>,
+    streak_credit: Option<StreakCredit>,
+    hard_behaviour: HardBehaviour,
 }
 
 impl<'de> Deserialize<'de> for EaseReward {
@@ -26,21 +144,42 @@ impl<'de> Deserialize<'de> for EaseReward {
             step_ease_reward: f64,
             minimum_ease: f64,
             maximum_ease: f64,
+            #[serde(default)]
+            ease_gravity: Option<EaseGravity>,
+            #[serde(default)]
+            streak_credit: Option<StreakCredit>,
+            #[serde(default)]
+            hard_behaviour: HardBehaviour,
         }
 
         let fields = Fields::deserialize(deserializer)?;
 
-        Ok(EaseReward::new(
+        let mut ease_reward = EaseReward::new(
             fields.minimum_consecutive_successful_reviews_required_for_reward,
             fields.base_ease_reward,
             fields.step_ease_reward,
             fields.minimum_ease,
             fields.maximum_ease,
-        ))
+        );
+        ease_reward.ease_gravity = fields.ease_gravity;
+        ease_reward.streak_credit = fields.streak_credit;
+        ease_reward.hard_behaviour = fields.hard_behaviour;
+        Ok(ease_reward)
     }
 }
 
 impl EaseReward {
+    pub const FIELDS: &'static [&'static str] = &[
+        "minimumConsecutiveSuccessfulReviewsRequiredForReward",
+        "baseEaseReward",
+        "stepEaseReward",
+        "minimumEase",
+        "maximumEase",
+        "easeGravity",
+        "streakCredit",
+        "hardBehaviour",
+    ];
+
     pub fn new(
         minimum_consecutive_successful_reviews_required_for_reward: u32,
         base_ease_reward: f64,
@@ -50,11 +189,152 @@ impl EaseReward {
     ) -> Self {
         Self {
             minimum_consecutive_successful_reviews_required_for_reward,
-            base_ease_reward: base_ease_reward.max(0.0),
-            step_ease_reward: step_ease_reward.max(0.0),
-            minimum_ease: minimum_ease.max(1.3),
-            maximum_ease: maximum_ease.clamp(0.0, 9.99),
+            base_ease_reward,
+            step_ease_reward,
+            minimum_ease,
+            maximum_ease,
+            ease_gravity: None,
+            streak_credit: None,
+            hard_behaviour: HardBehaviour::default(),
+        }
+    }
+
+    pub fn with_ease_gravity(mut self, ease_gravity: EaseGravity) -> Self {
+        self.ease_gravity = Some(ease_gravity);
+        self
+    }
+
+    pub fn with_streak_credit(mut self, streak_credit: StreakCredit) -> Self {
+        self.streak_credit = Some(streak_credit);
+        self
+    }
+
+    pub fn with_hard_behaviour(mut self, hard_behaviour: HardBehaviour) -> Self {
+        self.hard_behaviour = hard_behaviour;
+        self
+    }
+
+    pub fn hard_behaviour(&self) -> HardBehaviour {
+        self.hard_behaviour
+    }
+
+    /// Return the credit that a successful review adds to the streak of
+    /// consecutive successful reviews, which is always 1 without a streak
+    /// credit.
+    pub fn streak_credit(&self, scheduled_days: u32, elapsed_days: u32) -> u32 {
+        self.streak_credit.map_or(1, |streak_credit| {
+            streak_credit.credit(scheduled_days, elapsed_days)
+        })
+    }
+
+    pub fn validate(&self) -> Vec<InvalidField> {
+        let mut invalid_fields = Vec::new();
+        for (field, value) in [
+            ("baseEaseReward", self.base_ease_reward),
+            ("stepEaseReward", self.step_ease_reward),
+            ("minimumEase", self.minimum_ease),
+            ("maximumEase", self.maximum_ease),
+        ] {
+            if !value.is_finite() {
+                invalid_fields.push(InvalidField::new(field, format!("{value} is not a number")));
+            }
+        }
+        if self.base_ease_reward < 0.0 {
+            invalid_fields.push(InvalidField::new(
+                "baseEaseReward",
+                format!("{} is negative", self.base_ease_reward),
+            ));
+        }
+        if self.step_ease_reward < 0.0 {
+            invalid_fields.push(InvalidField::new(
+                "stepEaseReward",
+                format!("{} is negative", self.step_ease_reward),
+            ));
+        }
+        if self.minimum_ease < ABSOLUTE_MINIMUM_EASE {
+            invalid_fields.push(InvalidField::new(
+                "minimumEase",
+                format!("{} is below {}", self.minimum_ease, ABSOLUTE_MINIMUM_EASE),
+            ));
+        }
+        if self.maximum_ease > ABSOLUTE_MAXIMUM_EASE {
+            invalid_fields.push(InvalidField::new(
+                "maximumEase",
+                format!("{} exceeds {}", self.maximum_ease, ABSOLUTE_MAXIMUM_EASE),
+            ));
+        }
+        if self.minimum_ease > self.maximum_ease {
+            invalid_fields.push(InvalidField::new(
+                "minimumEase",
+                format!(
+                    "{} exceeds maximumEase {}",
+                    self.minimum_ease, self.maximum_ease
+                ),
+            ));
+        }
+        if let Some(ease_gravity) = &self.ease_gravity {
+            if !ease_gravity.strength.is_finite() || !(0.0..=1.0).contains(&ease_gravity.strength) {
+                invalid_fields.push(InvalidField::new(
+                    "easeGravity.strength",
+                    format!("{} is not between 0 and 1", ease_gravity.strength),
+                ));
+            }
+            if !ease_gravity.target_ease.is_finite()
+                || ease_gravity.target_ease < self.minimum_ease
+                || ease_gravity.target_ease > self.maximum_ease
+            {
+                invalid_fields.push(InvalidField::new(
+                    "easeGravity.targetEase",
+                    format!(
+                        "{} is not between minimumEase {} and maximumEase {}",
+                        ease_gravity.target_ease, self.minimum_ease, self.maximum_ease
+                    ),
+                ));
+            }
+        }
+        if let Some(streak_credit) = &self.streak_credit {
+            if !streak_credit.overdue_credit.is_finite() || streak_credit.overdue_credit < 0.0 {
+                invalid_fields.push(InvalidField::new(
+                    "streakCredit.overdueCredit",
+                    format!("{} is not a positive number", streak_credit.overdue_credit),
+                ));
+            }
         }
+        invalid_fields
+    }
+
+    fn base_ease_reward(&self) -> f64 {
+        self.base_ease_reward.max(0.0)
+    }
+
+    fn step_ease_reward(&self) -> f64 {
+        self.step_ease_reward.max(0.0)
+    }
+
+    fn minimum_ease(&self) -> f64 {
+        self.minimum_ease.max(ABSOLUTE_MINIMUM_EASE)
+    }
+
+    fn maximum_ease(&self) -> f64 {
+        self.maximum_ease.clamp(0.0, ABSOLUTE_MAXIMUM_EASE)
+    }
+
+    /// Move the ease factor toward the target ease of the ease gravity, if
+    /// any. Since the target ease is between the minimum and maximum ease, the
+    /// ease factor never moves past either of them.
+    fn apply_ease_gravity(&self, ease_factor: f64) -> f64 {
+        let Some(ease_gravity) = &self.ease_gravity else {
+            return ease_factor;
+        };
+        let target_ease = ease_gravity.target_ease.clamp(
+            self.minimum_ease(),
+            self.maximum_ease().max(self.minimum_ease()),
+        );
+        // Anki only stores up to the 3rd decimal place for the ease factor
+        round_to_places(
+            ease_factor + ease_gravity.strength() * (target_ease - ease_factor),
+            3,
+        )
     }
 
     pub fn calculate_new_ease_factor(
@@ -62,25 +342,26 @@ impl EaseReward {
         number_of_successful_reviews: u32,
         ease_factor: f64,
     ) -> f64 {
+        let ease_factor = self.apply_ease_gravity(ease_factor);
         if self.minimum_consecutive_successful_reviews_required_for_reward == 0
             || number_of_successful_reviews
                 < self.minimum_consecutive_successful_reviews_required_for_reward
-            || ease_factor < self.minimum_ease
-            || ease_factor > self.maximum_ease
+            || ease_factor < self.minimum_ease()
+            || ease_factor > self.maximum_ease()
         {
             return ease_factor;
         }
 
-        let reward = self.base_ease_reward
+        let reward = self.base_ease_reward()
             + (number_of_successful_reviews
                 - self.minimum_consecutive_successful_reviews_required_for_reward)
                 as f64
-                * self.step_ease_reward;
+                * self.step_ease_reward();
         // Anki only stores up to the 3rd decimal place for the ease factor
         round_to_places(
-            self.minimum_ease
+            self.minimum_ease()
                 .max(ease_factor + reward)
-                .min(self.maximum_ease),
+                .min(self.maximum_ease()),
             3,
         )
     }
@@ -88,7 +369,11 @@ impl EaseReward {
 
 #[cfg(test)]
 mod tests {
-    use crate::ease_reward::EaseReward;
+    use crate::card::Answer;
+    use crate::ease_reward::{
+        next_consecutive_successful_reviews, EaseGravity, EaseReward, HardBehaviour, StreakCredit,
+    };
+    use crate::validation::{deserialized_fields, InvalidField};
     use wasm_bindgen_test::wasm_bindgen_test;
 
     #[derive(Default)]
@@ -260,4 +545,233 @@ mod tests {
 
         assert_eq!(result, 1.2);
     }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn valid_ease_reward_has_no_invalid_fields() {
+        let under_test = EaseRewardBuilder::default()
+            .minimum_consecutive_successful_reviews_required_for_reward(3)
+            .base_ease_reward(0.05)
+            .step_ease_reward(0.05)
+            .minimum_ease(1.30)
+            .maximum_ease(2.50)
+            .build();
+
+        let result = under_test.validate();
+
+        assert_eq!(result, vec![]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn every_invalid_field_is_reported() {
+        let under_test = EaseRewardBuilder::default()
+            .minimum_consecutive_successful_reviews_required_for_reward(3)
+            .base_ease_reward(-0.05)
+            .step_ease_reward(0.05)
+            .minimum_ease(1.00)
+            .maximum_ease(12.0)
+            .build();
+
+        let result = under_test.validate();
+
+        assert_eq!(
+            result,
+            vec![
+                InvalidField::new("baseEaseReward", "-0.05 is negative".to_string()),
+                InvalidField::new("minimumEase", "1 is below 1.3".to_string()),
+                InvalidField::new("maximumEase", "12 exceeds 9.99".to_string()),
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn minimum_ease_above_maximum_ease_is_reported() {
+        let under_test = EaseRewardBuilder::default()
+            .minimum_consecutive_successful_reviews_required_for_reward(3)
+            .base_ease_reward(0.05)
+            .step_ease_reward(0.05)
+            .minimum_ease(3.00)
+            .maximum_ease(2.50)
+            .build();
+
+        let result = under_test.validate();
+
+        assert_eq!(
+            result,
+            vec![InvalidField::new(
+                "minimumEase",
+                "3 exceeds maximumEase 2.5".to_string()
+            )]
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ease_gravity_moves_ease_factor_toward_target_ease() {
+        let under_test = EaseRewardBuilder::default()
+            .minimum_consecutive_successful_reviews_required_for_reward(0)
+            .base_ease_reward(0.15)
+            .step_ease_reward(0.05)
+            .minimum_ease(1.30)
+            .maximum_ease(3.00)
+            .build()
+            .with_ease_gravity(EaseGravity::new(2.5, 0.1));
+
+        let result = under_test.calculate_new_ease_factor(1, 2.0);
+
+        assert_eq!(result, 2.05);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ease_reward_is_added_after_ease_gravity() {
+        let under_test = EaseRewardBuilder::default()
+            .minimum_consecutive_successful_reviews_required_for_reward(2)
+            .base_ease_reward(0.05)
+            .step_ease_reward(0.05)
+            .minimum_ease(1.30)
+            .maximum_ease(2.50)
+            .build()
+            .with_ease_gravity(EaseGravity::new(2.5, 0.2));
+
+        let result = under_test.calculate_new_ease_factor(3, 1.5);
+
+        assert_eq!(result, 1.8);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ease_gravity_lowers_ease_factor_above_maximum() {
+        let under_test = EaseRewardBuilder::default()
+            .minimum_consecutive_successful_reviews_required_for_reward(1)
+            .base_ease_reward(0.05)
+            .step_ease_reward(0.05)
+            .minimum_ease(1.30)
+            .maximum_ease(2.50)
+            .build()
+            .with_ease_gravity(EaseGravity::new(2.5, 0.5));
+
+        let result = under_test.calculate_new_ease_factor(3, 3.0);
+
+        assert_eq!(result, 2.75);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn invalid_ease_gravity_is_reported() {
+        let under_test = EaseRewardBuilder::default()
+            .minimum_consecutive_successful_reviews_required_for_reward(3)
+            .base_ease_reward(0.05)
+            .step_ease_reward(0.05)
+            .minimum_ease(1.30)
+            .maximum_ease(2.50)
+            .build()
+            .with_ease_gravity(EaseGravity::new(2.7, 1.5));
+
+        let result = under_test.validate();
+
+        assert_eq!(
+            result,
+            vec![
+                InvalidField::new(
+                    "easeGravity.strength",
+                    "1.5 is not between 0 and 1".to_string()
+                ),
+                InvalidField::new(
+                    "easeGravity.targetEase",
+                    "2.7 is not between minimumEase 1.3 and maximumEase 2.5".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn every_successful_review_earns_1_credit_without_streak_credit() {
+        let under_test = EaseRewardBuilder::default()
+            .minimum_consecutive_successful_reviews_required_for_reward(3)
+            .base_ease_reward(0.05)
+            .step_ease_reward(0.05)
+            .minimum_ease(1.30)
+            .maximum_ease(2.50)
+            .build();
+
+        let result = under_test.streak_credit(1, 30);
+
+        assert_eq!(result, 1);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn successful_review_below_minimum_interval_earns_no_credit() {
+        let under_test = StreakCredit::new(3, 1.0);
+
+        let result = under_test.credit(2, 2);
+
+        assert_eq!(result, 0);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn overdue_successful_review_earns_more_credit() {
+        let under_test = StreakCredit::new(3, 1.0);
+
+        let on_time = under_test.credit(10, 10);
+        let overdue = under_test.credit(10, 19);
+        let very_overdue = under_test.credit(10, 30);
+
+        assert_eq!(on_time, 1);
+        assert_eq!(overdue, 1);
+        assert_eq!(very_overdue, 3);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn huge_streak_credit_saturates() {
+        let under_test = StreakCredit::new(1, 1e300);
+
+        let credit = under_test.credit(1, 100);
+        let result = next_consecutive_successful_reviews(
+            Answer::Good,
+            Some(3),
+            credit,
+            HardBehaviour::Preserve,
+        );
+
+        assert_eq!(credit, u32::MAX);
+        assert_eq!(result, Some(u32::MAX));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn hard_behaviour_changes_the_streak() {
+        let result = [
+            HardBehaviour::Preserve,
+            HardBehaviour::Reset,
+            HardBehaviour::Decrement,
+            HardBehaviour::CountAsSuccess,
+        ]
+        .map(|hard_behaviour| {
+            next_consecutive_successful_reviews(Answer::Hard, Some(3), 1, hard_behaviour)
+        });
+
+        assert_eq!(result, [Some(3), None, Some(2), Some(4)]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn decrementing_the_last_successful_review_removes_the_streak() {
+        let result =
+            next_consecutive_successful_reviews(Answer::Hard, Some(1), 1, HardBehaviour::Decrement);
+
+        assert_eq!(result, None);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn again_always_removes_the_streak() {
+        let result = next_consecutive_successful_reviews(
+            Answer::Again,
+            Some(3),
+            1,
+            HardBehaviour::CountAsSuccess,
+        );
+
+        assert_eq!(result, None);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn known_fields_match_deserialized_fields() {
+        assert_eq!(deserialized_fields::<EaseGravity>(), EaseGravity::FIELDS);
+        assert_eq!(deserialized_fields::<StreakCredit>(), StreakCredit::FIELDS);
+        assert_eq!(deserialized_fields::<EaseReward>(), EaseReward::FIELDS);
+    }
 }
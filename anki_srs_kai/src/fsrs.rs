@@ -0,0 +1,213 @@
+use crate::card::Answer;
+
+// The code below is a port of the FSRS-6 memory model used by the py-fsrs
+// package, which is what `fsrs_to_sm2.ipynb` uses to approximate FSRS with the
+// multipliers of the custom scheduler
+//
+// See: https://github.com/open-spaced-repetition/py-fsrs/blob/v6.1.1/fsrs/scheduler.py
+
+const MINIMUM_STABILITY: f64 = 0.001;
+const MINIMUM_DIFFICULTY: f64 = 1.0;
+const MAXIMUM_DIFFICULTY: f64 = 10.0;
+
+/// How well a card is remembered according to FSRS.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MemoryState {
+    /// The number of days until the probability of recalling the card drops to
+    /// 90%.
+    pub stability: f64,
+    pub difficulty: f64,
+}
+
+/// The FSRS-6 memory model with a set of trained parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fsrs {
+    parameters: [f64; 21],
+}
+
+impl Default for Fsrs {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_PARAMETERS)
+    }
+}
+
+impl Fsrs {
+    pub const DEFAULT_PARAMETERS: [f64; 21] = [
+        0.212, 1.2931, 2.3065, 8.2956, 6.4133, 0.8334, 3.0194, 0.001, 1.8722, 0.1666, 0.796,
+        1.4835, 0.0614, 0.2629, 1.6483, 0.6014, 1.8729, 0.5425, 0.0912, 0.0658, 0.1542,
+    ];
+
+    pub fn new(parameters: [f64; 21]) -> Self {
+        Self { parameters }
+    }
+
+    fn decay(&self) -> f64 {
+        -self.parameters[20]
+    }
+
+    fn factor(&self) -> f64 {
+        0.9_f64.powf(1.0 / self.decay()) - 1.0
+    }
+
+    /// The probability of recalling a card `elapsed_days` after it was last
+    /// reviewed.
+    pub fn retrievability(&self, stability: f64, elapsed_days: f64) -> f64 {
+        (1.0 + self.factor() * elapsed_days / stability).powf(self.decay())
+    }
+
+    /// The interval, in days, after which the probability of recalling a card
+    /// drops to `desired_retention`. The interval is not rounded.
+    pub fn next_interval(&self, stability: f64, desired_retention: f64) -> f64 {
+        stability / self.factor() * (desired_retention.powf(1.0 / self.decay()) - 1.0)
+    }
+
+    pub fn initial_stability(&self, answer: Answer) -> f64 {
+        self.parameters[rating(answer) as usize - 1].max(MINIMUM_STABILITY)
+    }
+
+    pub fn initial_difficulty(&self, answer: Answer) -> f64 {
+        self.unclamped_initial_difficulty(answer)
+            .clamp(MINIMUM_DIFFICULTY, MAXIMUM_DIFFICULTY)
+    }
+
+    fn unclamped_initial_difficulty(&self, answer: Answer) -> f64 {
+        self.parameters[4] - (self.parameters[5] * (rating(answer) - 1.0)).exp() + 1.0
+    }
+
+    pub fn initial_memory_state(&self, answer: Answer) -> MemoryState {
+        MemoryState {
+            stability: self.initial_stability(answer),
+            difficulty: self.initial_difficulty(answer),
+        }
+    }
+
+    /// The memory state of a card after answering it `elapsed_days` after it
+    /// was last reviewed.
+    pub fn next_memory_state(
+        &self,
+        memory_state: MemoryState,
+        elapsed_days: f64,
+        answer: Answer,
+    ) -> MemoryState {
+        let retrievability = self.retrievability(memory_state.stability, elapsed_days);
+        let stability = match answer {
+            Answer::Again => self.next_forget_stability(
+                memory_state.difficulty,
+                memory_state.stability,
+                retrievability,
+            ),
+            Answer::Hard | Answer::Good | Answer::Easy => self.next_recall_stability(
+                memory_state.difficulty,
+                memory_state.stability,
+                retrievability,
+                answer,
+            ),
+        };
+        MemoryState {
+            stability,
+            difficulty: self.next_difficulty(memory_state.difficulty, answer),
+        }
+    }
+
+    pub fn next_recall_stability(
+        &self,
+        difficulty: f64,
+        stability: f64,
+        retrievability: f64,
+        answer: Answer,
+    ) -> f64 {
+        let w = &self.parameters;
+        let hard_penalty = if answer == Answer::Hard { w[15] } else { 1.0 };
+        let easy_bonus = if answer == Answer::Easy { w[16] } else { 1.0 };
+        (stability
+            * (1.0
+                + w[8].exp()
+                    * (11.0 - difficulty)
+                    * stability.powf(-w[9])
+                    * (((1.0 - retrievability) * w[10]).exp() - 1.0)
+                    * hard_penalty
+                    * easy_bonus))
+            .max(MINIMUM_STABILITY)
+    }
+
+    pub fn next_forget_stability(
+        &self,
+        difficulty: f64,
+        stability: f64,
+        retrievability: f64,
+    ) -> f64 {
+        let w = &self.parameters;
+        let long_term = w[11]
+            * difficulty.powf(-w[12])
+            * ((stability + 1.0).powf(w[13]) - 1.0)
+            * ((1.0 - retrievability) * w[14]).exp();
+        let short_term = stability / (w[17] * w[18]).exp();
+        long_term.min(short_term).max(MINIMUM_STABILITY)
+    }
+
+    pub fn next_difficulty(&self, difficulty: f64, answer: Answer) -> f64 {
+        let w = &self.parameters;
+        let delta = -(w[6] * (rating(answer) - 3.0));
+        let damped = difficulty + (10.0 - difficulty) * delta / 9.0;
+        let mean_reversion =
+            w[7] * self.unclamped_initial_difficulty(Answer::Easy) + (1.0 - w[7]) * damped;
+        mean_reversion.clamp(MINIMUM_DIFFICULTY, MAXIMUM_DIFFICULTY)
+    }
+}
+
+fn rating(answer: Answer) -> f64 {
+    match answer {
+        Answer::Again => 1.0,
+        Answer::Hard => 2.0,
+        Answer::Good => 3.0,
+        Answer::Easy => 4.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::Answer;
+    use crate::fsrs::Fsrs;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn retrievability_is_90_percent_after_stability_days() {
+        let under_test = Fsrs::default();
+
+        let result = under_test.retrievability(10.0, 10.0);
+
+        assert!((result - 0.9).abs() < 1e-9);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn next_interval_is_stability_at_90_percent_desired_retention() {
+        let under_test = Fsrs::default();
+
+        let result = under_test.next_interval(10.0, 0.9);
+
+        assert!((result - 10.0).abs() < 1e-9);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn initial_memory_state_uses_rating_parameters() {
+        let under_test = Fsrs::default();
+
+        let result = under_test.initial_memory_state(Answer::Good);
+
+        assert_eq!(result.stability, 2.3065);
+        assert!((result.difficulty - (6.4133 - (0.8334_f64 * 2.0).exp() + 1.0)).abs() < 1e-9);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn forgetting_reduces_stability_and_recalling_increases_it() {
+        let under_test = Fsrs::default();
+        let memory_state = under_test.initial_memory_state(Answer::Good);
+
+        let forgotten = under_test.next_memory_state(memory_state, 3.0, Answer::Again);
+        let recalled = under_test.next_memory_state(memory_state, 3.0, Answer::Good);
+
+        assert!(forgotten.stability < memory_state.stability);
+        assert!(forgotten.difficulty > memory_state.difficulty);
+        assert!(recalled.stability > memory_state.stability);
+    }
+}
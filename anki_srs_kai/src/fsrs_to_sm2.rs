@@ -0,0 +1,150 @@
+use crate::card::Answer;
+use crate::fsrs::Fsrs;
+
+// The code below is a port of `fsrs_to_sm2.ipynb`, which approximates the
+// stability increase of FSRS with `startingEase * currentInterval ^ a + b`
+
+const MINIMUM_INTERVAL: f64 = 0.01;
+const MAXIMUM_INTERVAL: f64 = 3650.0;
+const NUMBER_OF_INTERVALS: usize = 100_000;
+// The intervals are assumed to have 90% retrievability when converting to SM-2
+const RETRIEVABILITY: f64 = 0.9;
+// Large enough that rounding the interval does not affect the interval modifier
+const INTERVAL_MODIFIER_STABILITY: f64 = 1_000_000.0;
+
+/// The curve of best fit `startingEase * currentInterval ^ a + b` for the
+/// stability increase of one of the answer buttons, and how well it fits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PowerLawFit {
+    pub a: f64,
+    pub b: f64,
+    pub mean_squared_error: f64,
+    pub mean_absolute_error: f64,
+    pub root_mean_squared_error: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sm2Approximation {
+    pub interval_modifier: f64,
+    pub graduating_interval_again: f64,
+    pub graduating_interval_hard: f64,
+    pub graduating_interval_good: f64,
+    pub easy_interval: f64,
+    pub hard: PowerLawFit,
+    pub good: PowerLawFit,
+    pub easy: PowerLawFit,
+}
+
+/// Approximate FSRS with the multipliers of the custom scheduler.
+pub fn fsrs_to_sm2(fsrs: &Fsrs, starting_ease: f64, desired_retention: f64) -> Sm2Approximation {
+    let intervals = (0..NUMBER_OF_INTERVALS)
+        .map(|i| {
+            MINIMUM_INTERVAL
+                + (MAXIMUM_INTERVAL - MINIMUM_INTERVAL) * i as f64
+                    / (NUMBER_OF_INTERVALS - 1) as f64
+        })
+        .collect::<Vec<f64>>();
+    let fit = |answer| {
+        let difficulty = fsrs.initial_difficulty(answer);
+        let stability_increases = intervals
+            .iter()
+            .map(|&interval| {
+                fsrs.next_recall_stability(difficulty, interval, RETRIEVABILITY, answer) / interval
+            })
+            .collect::<Vec<f64>>();
+        fit_power_law(&intervals, &stability_increases, starting_ease)
+    };
+
+    let interval_modifier = fsrs
+        .next_interval(INTERVAL_MODIFIER_STABILITY, desired_retention)
+        .round()
+        / INTERVAL_MODIFIER_STABILITY;
+
+    Sm2Approximation {
+        interval_modifier,
+        graduating_interval_again: fsrs.initial_stability(Answer::Again) * interval_modifier,
+        graduating_interval_hard: fsrs.initial_stability(Answer::Hard) * interval_modifier,
+        graduating_interval_good: fsrs.initial_stability(Answer::Good) * interval_modifier,
+        easy_interval: fsrs.initial_stability(Answer::Easy) * interval_modifier,
+        hard: fit(Answer::Hard),
+        good: fit(Answer::Good),
+        easy: fit(Answer::Easy),
+    }
+}
+
+/// Least squares fit of `k * x ^ a + b`. For a given exponent `a`, the best
+/// offset `b` is the mean of the residuals, so only `a` has to be searched for.
+fn fit_power_law(x: &[f64], y: &[f64], k: f64) -> PowerLawFit {
+    let offset =
+        |a: f64| x.iter().zip(y).map(|(x, y)| y - k * x.powf(a)).sum::<f64>() / x.len() as f64;
+    let sum_of_squared_errors = |a: f64| {
+        let b = offset(a);
+        x.iter()
+            .zip(y)
+            .map(|(x, y)| (y - k * x.powf(a) - b).powi(2))
+            .sum::<f64>()
+    };
+
+    // Coarse search for the region of the minimum, then refine it with a
+    // golden section search
+    let step = 0.1;
+    let best = (-20..=20)
+        .map(|i| i as f64 * step)
+        .map(|a| (a, sum_of_squared_errors(a)))
+        .min_by(|(_, left), (_, right)| left.total_cmp(right))
+        .map_or(0.0, |(a, _)| a);
+    let inverse_golden_ratio = (5_f64.sqrt() - 1.0) / 2.0;
+    let (mut lower, mut upper) = (best - step, best + step);
+    while upper - lower > 1e-12 {
+        let left = upper - inverse_golden_ratio * (upper - lower);
+        let right = lower + inverse_golden_ratio * (upper - lower);
+        if sum_of_squared_errors(left) < sum_of_squared_errors(right) {
+            upper = right;
+        } else {
+            lower = left;
+        }
+    }
+    let a = (lower + upper) / 2.0;
+    let b = offset(a);
+
+    let (squared_error, absolute_error) =
+        x.iter()
+            .zip(y)
+            .fold((0.0, 0.0), |(squared_error, absolute_error), (x, y)| {
+                let error = y - (k * x.powf(a) + b);
+                (squared_error + error * error, absolute_error + error.abs())
+            });
+    let mean_squared_error = squared_error / x.len() as f64;
+    PowerLawFit {
+        a,
+        b,
+        mean_squared_error,
+        mean_absolute_error: absolute_error / x.len() as f64,
+        root_mean_squared_error: mean_squared_error.sqrt(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fsrs::Fsrs;
+    use crate::fsrs_to_sm2::fsrs_to_sm2;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn default_fsrs_parameters_match_the_notebook() {
+        let result = fsrs_to_sm2(&Fsrs::default(), 2.5, 0.9);
+
+        assert_eq!(result.interval_modifier, 1.0);
+        assert_eq!(result.graduating_interval_good.round(), 2.0);
+        assert_eq!(result.easy_interval.round(), 8.0);
+        // Within the tolerance of the curve fitting used by the notebook
+        for (fit, a, b) in [
+            (&result.hard, -0.077098162, 0.144440985),
+            (&result.good, -0.182458510, 1.779479164),
+            (&result.easy, -0.183552566, 3.407921198),
+        ] {
+            assert!((fit.a - a).abs() < 1e-5, "{} != {a}", fit.a);
+            assert!((fit.b - b).abs() < 1e-5, "{} != {b}", fit.b);
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use serde::Deserialize;
+
+use crate::card::RelearningCard;
+use crate::leech_policy::LeechPolicy;
+use crate::load_balancer::LoadBalancer;
+use crate::multiplier::{Multiplier, MultiplierContext, MultiplierError};
+use crate::scheduler::{NextState, Scheduler};
+use crate::validation::InvalidField;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lapse {
+    calculate_lapse_multiplier: Multiplier,
+}
+
+impl Lapse {
+    pub const FIELDS: &'static [&'static str] = &["calculateLapseMultiplier"];
+
+    pub fn new(calculate_lapse_multiplier: Multiplier) -> Self {
+        Self {
+            calculate_lapse_multiplier,
+        }
+    }
+
+    pub fn validate(&self) -> Vec<InvalidField> {
+        self.calculate_lapse_multiplier
+            .validate("calculateLapseMultiplier")
+    }
+
+    /// Calculate the intervals of a relearning card graduating back to review.
+    /// The lapse multiplier function is called with the ease factor, the
+    /// interval before the lapse, the number of lapses, and the card as a
+    /// context object. Leeches are kept from growing back to long intervals by
+    /// the leech policy, if one is given.
+    pub fn next_states(
+        &self,
+        scheduler: &Scheduler,
+        fuzz_seed: u64,
+        card: &RelearningCard,
+        leech_policy: Option<&LeechPolicy>,
+        load_balancer: Option<&LoadBalancer>,
+    ) -> Result<NextState, MultiplierError> {
+        let context = MultiplierContext::relearning(card, fuzz_seed);
+        let lapse_multiplier = self.calculate_lapse_multiplier.calculate(
+            "calculateLapseMultiplier",
+            card.ease_factor,
+            card.previous_interval,
+            &[card.lapses as f64],
+            Some(&context),
+        )?;
+        Ok(scheduler.relearning_graduation_states(
+            fuzz_seed,
+            card,
+            lapse_multiplier,
+            leech_policy,
+            load_balancer,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::RelearningCard;
+    use crate::lapse::Lapse;
+    use crate::leech_policy::LeechPolicy;
+    use crate::multiplier::constant_multiplier;
+    use crate::scheduler::{GrowthCeiling, NextState, Scheduler};
+    use crate::validation::deserialized_fields;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn relearning_card() -> RelearningCard {
+        RelearningCard {
+            deck_name: "Japanese".to_string(),
+            ease_factor: 2.5,
+            previous_interval: 100,
+            lapses: 1,
+            leeched: false,
+            memory_state: None,
+        }
+    }
+
+    fn scheduler(maximum_interval: u32) -> Scheduler {
+        Scheduler::new(
+            false,
+            maximum_interval,
+            1.0,
+            constant_multiplier(1.2),
+            constant_multiplier(2.5),
+            constant_multiplier(3.25),
+        )
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn interval_is_lapse_multiplier_of_previous_interval() {
+        let under_test = Lapse::new(constant_multiplier(0.3));
+
+        let result = under_test.next_states(&scheduler(36500), 123, &relearning_card(), None, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: None,
+                good_interval: Some(30),
+                easy_interval: Some(31),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn interval_is_clamped_to_maximum_interval() {
+        let under_test = Lapse::new(constant_multiplier(0.5));
+
+        let result = under_test.next_states(&scheduler(20), 123, &relearning_card(), None, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: None,
+                good_interval: Some(20),
+                easy_interval: Some(20),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn lapse_multiplier_of_0_is_left_to_anki() {
+        let under_test = Lapse::new(constant_multiplier(0.0));
+
+        let result = under_test.next_states(&scheduler(36500), 123, &relearning_card(), None, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: None,
+                good_interval: None,
+                easy_interval: None,
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn leech_interval_is_clamped_to_leech_maximum_interval() {
+        let under_test = Lapse::new(constant_multiplier(0.5));
+        let leech_policy = LeechPolicy::new(None, Some(20), None, None);
+        let card = RelearningCard {
+            lapses: 8,
+            leeched: true,
+            ..relearning_card()
+        };
+
+        let result =
+            under_test.next_states(&scheduler(36500), 123, &card, Some(&leech_policy), None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: None,
+                good_interval: Some(20),
+                easy_interval: Some(20),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn interval_is_shrunk_by_lapse_interval_modifier() {
+        let under_test = Lapse::new(constant_multiplier(0.5));
+        let leech_policy = LeechPolicy::new(None, None, Some(0.5), None);
+        let card = RelearningCard {
+            lapses: 2,
+            ..relearning_card()
+        };
+
+        let result =
+            under_test.next_states(&scheduler(36500), 123, &card, Some(&leech_policy), None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: None,
+                good_interval: Some(13),
+                easy_interval: Some(14),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn interval_growth_is_limited_by_growth_ceiling() {
+        let under_test = Lapse::new(constant_multiplier(3.0));
+        let scheduler = scheduler(36500).with_growth_ceiling(GrowthCeiling::new(Some(1.5), None));
+
+        let result = under_test.next_states(&scheduler, 123, &relearning_card(), None, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: None,
+                good_interval: Some(150),
+                easy_interval: Some(150),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn known_fields_match_deserialized_fields() {
+        assert_eq!(deserialized_fields::<Lapse>(), Lapse::FIELDS);
+    }
+}
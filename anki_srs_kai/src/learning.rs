@@ -0,0 +1,244 @@
+#[cfg(feature = "wasm")]
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::load_balancer::LoadBalancer;
+#[cfg(feature = "wasm")]
+use crate::multiplier::call_function;
+use crate::multiplier::{check_multiplier, MultiplierError};
+use crate::scheduler::{NextState, Scheduler};
+use crate::validation::InvalidField;
+
+/// The interval in days of a learning card graduating to review. It can either
+/// be a fixed number of days, or a JavaScript function taking in the remaining
+/// learning steps and the seconds elapsed since the card was last answered.
+pub enum LearningInterval {
+    Fixed(f64),
+    #[cfg(feature = "wasm")]
+    Function(js_sys::Function),
+}
+
+#[cfg(feature = "wasm")]
+impl<'de> Deserialize<'de> for LearningInterval {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value: JsValue = serde_wasm_bindgen::preserve::deserialize(deserializer)?;
+        if value.is_function() {
+            Ok(LearningInterval::Function(value.unchecked_into()))
+        } else {
+            value
+                .as_f64()
+                .map(LearningInterval::Fixed)
+                .ok_or_else(|| D::Error::custom("expected a number or a function"))
+        }
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+impl<'de> Deserialize<'de> for LearningInterval {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        f64::deserialize(deserializer).map(LearningInterval::Fixed)
+    }
+}
+
+impl LearningInterval {
+    fn validate(&self, function_name: &'static str) -> Option<InvalidField> {
+        match self {
+            LearningInterval::Fixed(interval) => check_multiplier(*interval).err().map(|_| {
+                InvalidField::new(function_name, format!("{interval} is not a number of days"))
+            }),
+            #[cfg(feature = "wasm")]
+            LearningInterval::Function(_) => None,
+        }
+    }
+
+    #[cfg_attr(not(feature = "wasm"), allow(unused_variables))]
+    fn calculate(
+        &self,
+        function_name: &'static str,
+        remaining_steps: u32,
+        elapsed_secs: u32,
+    ) -> Result<f64, MultiplierError> {
+        let to_error = |reason| MultiplierError::new(function_name, reason);
+        let interval = match self {
+            LearningInterval::Fixed(interval) => *interval,
+            #[cfg(feature = "wasm")]
+            LearningInterval::Function(function) => {
+                let arguments = js_sys::Array::of2(
+                    &JsValue::from(remaining_steps),
+                    &JsValue::from(elapsed_secs),
+                );
+                call_function(function, &arguments).map_err(to_error)?
+            }
+        };
+        check_multiplier(interval).map_err(to_error)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Learning {
+    #[serde(default)]
+    graduating_interval: Option<LearningInterval>,
+    #[serde(default)]
+    easy_interval: Option<LearningInterval>,
+}
+
+impl Learning {
+    pub const FIELDS: &'static [&'static str] = &["graduatingInterval", "easyInterval"];
+
+    /// `graduating_interval` is used after pressing Good on the last learning
+    /// step, and `easy_interval` after pressing Easy on any learning step.
+    pub fn new(
+        graduating_interval: Option<LearningInterval>,
+        easy_interval: Option<LearningInterval>,
+    ) -> Self {
+        Self {
+            graduating_interval,
+            easy_interval,
+        }
+    }
+
+    pub fn validate(&self) -> Vec<InvalidField> {
+        [
+            ("graduatingInterval", &self.graduating_interval),
+            ("easyInterval", &self.easy_interval),
+        ]
+        .into_iter()
+        .filter_map(|(field, interval)| interval.as_ref()?.validate(field))
+        .collect()
+    }
+
+    /// Calculate the intervals of a learning card graduating to review. An
+    /// interval that is not configured, or is 0, is left to Anki.
+    pub fn next_states(
+        &self,
+        scheduler: &Scheduler,
+        fuzz_seed: u64,
+        remaining_steps: u32,
+        elapsed_secs: u32,
+        load_balancer: Option<&LoadBalancer>,
+    ) -> Result<NextState, MultiplierError> {
+        let mut intervals = [None; 2];
+        for (interval, (function_name, learning_interval)) in intervals.iter_mut().zip([
+            ("graduatingInterval", &self.graduating_interval),
+            ("easyInterval", &self.easy_interval),
+        ]) {
+            if let Some(learning_interval) = learning_interval {
+                *interval = Some(learning_interval.calculate(
+                    function_name,
+                    remaining_steps,
+                    elapsed_secs,
+                )?);
+            }
+        }
+        let [graduating_interval, easy_interval] = intervals;
+        Ok(scheduler.learning_graduation_states(
+            fuzz_seed,
+            graduating_interval,
+            easy_interval,
+            load_balancer,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::learning::{Learning, LearningInterval};
+    use crate::multiplier::constant_multiplier;
+    use crate::scheduler::{NextState, Scheduler};
+    use crate::validation::{deserialized_fields, InvalidField};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn scheduler(maximum_interval: u32) -> Scheduler {
+        Scheduler::new(
+            false,
+            maximum_interval,
+            2.0,
+            constant_multiplier(1.2),
+            constant_multiplier(2.5),
+            constant_multiplier(3.25),
+        )
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fixed_intervals_are_used_without_interval_modifier() {
+        let under_test = Learning::new(
+            Some(LearningInterval::Fixed(3.0)),
+            Some(LearningInterval::Fixed(7.0)),
+        );
+
+        let result = under_test.next_states(&scheduler(36500), 123, 1, 600, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: None,
+                good_interval: Some(3),
+                easy_interval: Some(7),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn easy_interval_is_longer_than_graduating_interval() {
+        let under_test = Learning::new(
+            Some(LearningInterval::Fixed(3.0)),
+            Some(LearningInterval::Fixed(2.0)),
+        );
+
+        let result = under_test.next_states(&scheduler(36500), 123, 1, 600, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: None,
+                good_interval: Some(3),
+                easy_interval: Some(4),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn intervals_that_are_not_configured_are_left_to_anki() {
+        let under_test = Learning::new(None, Some(LearningInterval::Fixed(50.0)));
+
+        let result = under_test.next_states(&scheduler(20), 123, 1, 600, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: None,
+                good_interval: None,
+                easy_interval: Some(20),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn negative_fixed_interval_is_invalid() {
+        let under_test = Learning::new(Some(LearningInterval::Fixed(-1.0)), None);
+
+        let result = under_test.validate();
+
+        assert_eq!(
+            result,
+            vec![InvalidField::new(
+                "graduatingInterval",
+                "-1 is not a number of days".to_string()
+            )]
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn known_fields_match_deserialized_fields() {
+        assert_eq!(deserialized_fields::<Learning>(), Learning::FIELDS);
+    }
+}
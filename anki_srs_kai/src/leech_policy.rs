@@ -0,0 +1,162 @@
+use serde::Deserialize;
+
+use crate::validation::InvalidField;
+
+/// Keeps leeches and cards that lapse often from growing back to long
+/// intervals.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeechPolicy {
+    #[serde(default)]
+    leech_threshold: Option<u32>,
+    #[serde(default)]
+    maximum_interval: Option<u32>,
+    #[serde(default)]
+    lapse_interval_modifier: Option<f64>,
+    #[serde(default)]
+    suspend_ease_reward_after_lapses: Option<u32>,
+}
+
+impl LeechPolicy {
+    pub const FIELDS: &'static [&'static str] = &[
+        "leechThreshold",
+        "maximumInterval",
+        "lapseIntervalModifier",
+        "suspendEaseRewardAfterLapses",
+    ];
+
+    pub fn new(
+        leech_threshold: Option<u32>,
+        maximum_interval: Option<u32>,
+        lapse_interval_modifier: Option<f64>,
+        suspend_ease_reward_after_lapses: Option<u32>,
+    ) -> Self {
+        Self {
+            leech_threshold,
+            maximum_interval,
+            lapse_interval_modifier,
+            suspend_ease_reward_after_lapses,
+        }
+    }
+
+    pub fn validate(&self) -> Vec<InvalidField> {
+        let mut invalid_fields = Vec::new();
+        if self.leech_threshold == Some(0) {
+            invalid_fields.push(InvalidField::new(
+                "leechThreshold",
+                "0 is below 1".to_string(),
+            ));
+        }
+        if self.maximum_interval == Some(0) {
+            invalid_fields.push(InvalidField::new(
+                "maximumInterval",
+                "0 is below 1".to_string(),
+            ));
+        }
+        if let Some(lapse_interval_modifier) = self.lapse_interval_modifier {
+            if !lapse_interval_modifier.is_finite()
+                || lapse_interval_modifier <= 0.0
+                || lapse_interval_modifier > 1.0
+            {
+                invalid_fields.push(InvalidField::new(
+                    "lapseIntervalModifier",
+                    format!("{lapse_interval_modifier} is not greater than 0 and at most 1"),
+                ));
+            }
+        }
+        invalid_fields
+    }
+
+    /// Anki only marks the card as leeched on the answer that turns it into a
+    /// leech, so the number of lapses is also compared to the leech threshold.
+    fn is_leech(&self, lapses: u32, leeched: bool) -> bool {
+        leeched
+            || self
+                .leech_threshold
+                .is_some_and(|leech_threshold| lapses >= leech_threshold)
+    }
+
+    /// Return the maximum interval of the card, which is capped for leeches.
+    pub fn maximum_interval(&self, maximum_interval: u32, lapses: u32, leeched: bool) -> u32 {
+        match self.maximum_interval {
+            Some(leech_maximum_interval) if self.is_leech(lapses, leeched) => {
+                maximum_interval.min(leech_maximum_interval)
+            }
+            _ => maximum_interval,
+        }
+    }
+
+    /// Return the interval modifier of the card, which shrinks by the lapse
+    /// interval modifier for every lapse.
+    pub fn interval_modifier(&self, interval_modifier: f64, lapses: u32) -> f64 {
+        match self.lapse_interval_modifier {
+            Some(lapse_interval_modifier) => {
+                interval_modifier * lapse_interval_modifier.clamp(0.0, 1.0).powf(lapses as f64)
+            }
+            None => interval_modifier,
+        }
+    }
+
+    /// Whether the ease reward is no longer applied to the card.
+    pub fn suspends_ease_reward(&self, lapses: u32) -> bool {
+        self.suspend_ease_reward_after_lapses
+            .is_some_and(|minimum_lapses| lapses >= minimum_lapses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::leech_policy::LeechPolicy;
+    use crate::validation::{deserialized_fields, InvalidField};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn maximum_interval_is_capped_for_leeches() {
+        let under_test = LeechPolicy::new(Some(8), Some(30), None, None);
+
+        assert_eq!(under_test.maximum_interval(36500, 7, false), 36500);
+        assert_eq!(under_test.maximum_interval(36500, 8, false), 30);
+        assert_eq!(under_test.maximum_interval(36500, 2, true), 30);
+        assert_eq!(under_test.maximum_interval(20, 8, false), 20);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn interval_modifier_shrinks_per_lapse() {
+        let under_test = LeechPolicy::new(None, None, Some(0.5), None);
+
+        assert_eq!(under_test.interval_modifier(1.0, 0), 1.0);
+        assert_eq!(under_test.interval_modifier(1.0, 3), 0.125);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ease_reward_is_suspended_after_lapses() {
+        let under_test = LeechPolicy::new(None, None, None, Some(4));
+
+        assert!(!under_test.suspends_ease_reward(3));
+        assert!(under_test.suspends_ease_reward(4));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn every_invalid_field_is_reported() {
+        let under_test = LeechPolicy::new(Some(0), Some(0), Some(1.5), None);
+
+        let result = under_test.validate();
+
+        assert_eq!(
+            result,
+            vec![
+                InvalidField::new("leechThreshold", "0 is below 1".to_string()),
+                InvalidField::new("maximumInterval", "0 is below 1".to_string()),
+                InvalidField::new(
+                    "lapseIntervalModifier",
+                    "1.5 is not greater than 0 and at most 1".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn known_fields_match_deserialized_fields() {
+        assert_eq!(deserialized_fields::<LeechPolicy>(), LeechPolicy::FIELDS);
+    }
+}
@@ -0,0 +1,177 @@
+/// The number of days in a week, which is also the number of easy day weights.
+const DAYS_PER_WEEK: u32 = 7;
+
+/// Spreads the review intervals of cards across days, using the number of cards
+/// that are already due on each day and the easy day weights of the weekdays.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadBalancer {
+    due_counts: Vec<u32>,
+    weekday: Option<u32>,
+}
+
+impl LoadBalancer {
+    /// `due_counts[0]` is the number of cards due today, `due_counts[1]` the
+    /// number of cards due tomorrow, and so on.
+    pub fn new(due_counts: Vec<u32>) -> Self {
+        Self {
+            due_counts,
+            weekday: None,
+        }
+    }
+
+    /// The day of the week of today, from 0 for Sunday to 6 for Saturday, which
+    /// is needed to apply easy days.
+    pub fn with_weekday(mut self, weekday: u32) -> Self {
+        self.weekday = Some(weekday % DAYS_PER_WEEK);
+        self
+    }
+
+    /// Return the interval between `lower` and `upper` inclusive that has the
+    /// fewest cards due relative to the easy day weight of its weekday, using
+    /// the fuzz factor to break ties so that the same card always gets the
+    /// same interval. If the number of cards due is not known for every
+    /// interval in the range, the fuzz factor picks an interval in proportion
+    /// to the easy day weights instead. `None` if neither is known.
+    pub(crate) fn find_interval(
+        &self,
+        lower: u32,
+        upper: u32,
+        fuzz_factor: f64,
+        easy_days: Option<&[f64; 7]>,
+    ) -> Option<u32> {
+        let easy_day_weights = self.weekday.zip(easy_days).map(|(weekday, easy_days)| {
+            (lower..=upper)
+                .map(|interval| {
+                    easy_days[((weekday + interval) % DAYS_PER_WEEK) as usize].clamp(0.0, 1.0)
+                })
+                .collect::<Vec<f64>>()
+        });
+
+        let Some(due_counts) = self.due_counts.get(lower as usize..=upper as usize) else {
+            return easy_day_weights
+                .and_then(|weights| pick_weighted(lower, &weights, fuzz_factor));
+        };
+        let loads = due_counts
+            .iter()
+            .enumerate()
+            .map(|(i, due_count)| {
+                let weight = easy_day_weights.as_ref().map_or(1.0, |weights| weights[i]);
+                // Days with a weight of 0 are only used if every day has one
+                if weight == 0.0 {
+                    f64::INFINITY
+                } else {
+                    *due_count as f64 / weight
+                }
+            })
+            .collect::<Vec<f64>>();
+        let lightest_load = loads.iter().copied().fold(f64::INFINITY, f64::min);
+        let candidates = (lower..=upper)
+            .zip(&loads)
+            .filter(|(_, load)| **load == lightest_load)
+            .map(|(interval, _)| interval)
+            .collect::<Vec<u32>>();
+        if candidates.is_empty() {
+            // Every day has a weight of 0
+            return Some(lower + pick_index(upper - lower + 1, fuzz_factor));
+        }
+        candidates
+            .get(pick_index(candidates.len() as u32, fuzz_factor) as usize)
+            .copied()
+    }
+}
+
+fn pick_index(len: u32, fuzz_factor: f64) -> u32 {
+    ((fuzz_factor * len as f64) as u32).min(len.saturating_sub(1))
+}
+
+/// Pick the interval whose cumulative weight contains the fuzz factor, or
+/// `None` if every weight is 0.
+fn pick_weighted(lower: u32, weights: &[f64], fuzz_factor: f64) -> Option<u32> {
+    let total_weight = weights.iter().sum::<f64>();
+    if total_weight <= 0.0 {
+        return None;
+    }
+    let target = fuzz_factor * total_weight;
+    let mut cumulative_weight = 0.0;
+    for (interval, weight) in (lower..).zip(weights) {
+        cumulative_weight += weight;
+        if *weight > 0.0 && target < cumulative_weight {
+            return Some(interval);
+        }
+    }
+    (lower..)
+        .zip(weights)
+        .filter(|(_, weight)| **weight > 0.0)
+        .map(|(interval, _)| interval)
+        .last()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::load_balancer::LoadBalancer;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn interval_with_fewest_cards_due_is_used() {
+        let under_test = LoadBalancer::new(vec![50, 40, 30, 20, 10, 25, 15, 30]);
+
+        let result = under_test.find_interval(3, 6, 0.0, None);
+
+        assert_eq!(result, Some(4));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fuzz_factor_breaks_ties() {
+        let under_test = LoadBalancer::new(vec![50, 40, 10, 20, 10, 25, 10, 30]);
+
+        assert_eq!(under_test.find_interval(2, 6, 0.0, None), Some(2));
+        assert_eq!(under_test.find_interval(2, 6, 0.5, None), Some(4));
+        assert_eq!(under_test.find_interval(2, 6, 0.99, None), Some(6));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn no_interval_if_due_counts_are_unknown() {
+        let under_test = LoadBalancer::new(vec![50, 40, 30]);
+
+        let result = under_test.find_interval(2, 4, 0.0, None);
+
+        assert_eq!(result, None);
+    }
+
+    const WEEKENDS_OFF: [f64; 7] = [0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0];
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn cards_due_are_relative_to_easy_day_weight() {
+        // Today is a Wednesday, so the intervals 3 to 6 are Saturday to Tuesday
+        let under_test = LoadBalancer::new(vec![50, 40, 30, 5, 5, 30, 20]).with_weekday(3);
+
+        let result =
+            under_test.find_interval(3, 6, 0.0, Some(&[0.2, 1.0, 1.0, 1.0, 1.0, 1.0, 0.2]));
+
+        assert_eq!(result, Some(6));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn easy_days_are_avoided_without_due_counts() {
+        // Today is a Wednesday, so the intervals 3 to 5 are Saturday to Monday
+        let under_test = LoadBalancer::new(vec![]).with_weekday(3);
+
+        assert_eq!(
+            under_test.find_interval(3, 5, 0.0, Some(&WEEKENDS_OFF)),
+            Some(5)
+        );
+        assert_eq!(
+            under_test.find_interval(3, 5, 0.99, Some(&WEEKENDS_OFF)),
+            Some(5)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn easy_days_are_ignored_without_weekday() {
+        let under_test = LoadBalancer::new(vec![]);
+
+        let result = under_test.find_interval(3, 5, 0.0, Some(&WEEKENDS_OFF));
+
+        assert_eq!(result, None);
+    }
+}
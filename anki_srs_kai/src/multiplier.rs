@@ -0,0 +1,568 @@
+use std::fmt::{Display, Formatter};
+
+#[cfg(feature = "wasm")]
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::card::{RelearningCard, ReviewCard};
+use crate::validation::InvalidField;
+
+#[derive(Debug, PartialEq)]
+pub struct MultiplierError {
+    function_name: &'static str,
+    reason: String,
+}
+
+impl MultiplierError {
+    pub(crate) fn new(function_name: &'static str, reason: String) -> Self {
+        Self {
+            function_name,
+            reason,
+        }
+    }
+}
+
+impl Display for MultiplierError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.function_name, self.reason)
+    }
+}
+
+/// Reject multipliers that would produce nonsensical intervals.
+pub(crate) fn check_multiplier(multiplier: f64) -> Result<f64, String> {
+    if multiplier.is_nan() {
+        Err("returned NaN".to_string())
+    } else if multiplier.is_infinite() {
+        // Match how JavaScript displays infinite numbers
+        Err(format!(
+            "returned {}Infinity",
+            if multiplier < 0.0 { "-" } else { "" }
+        ))
+    } else if multiplier < 0.0 {
+        Err(format!("returned {multiplier} which is negative"))
+    } else {
+        Ok(multiplier)
+    }
+}
+
+/// The card being scheduled, which is either a review card or a relearning
+/// card graduating back to review.
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+enum ContextCard<'a> {
+    Review(&'a ReviewCard),
+    Relearning(&'a RelearningCard),
+}
+
+/// The card being scheduled, which is passed to multiplier functions as an
+/// object after all of their other arguments.
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+pub(crate) struct MultiplierContext<'a> {
+    card: ContextCard<'a>,
+    seed: u64,
+}
+
+impl<'a> MultiplierContext<'a> {
+    pub(crate) fn new(card: &'a ReviewCard, seed: u64) -> Self {
+        Self {
+            card: ContextCard::Review(card),
+            seed,
+        }
+    }
+
+    pub(crate) fn relearning(card: &'a RelearningCard, seed: u64) -> Self {
+        Self {
+            card: ContextCard::Relearning(card),
+            seed,
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    fn to_js_value(&self) -> JsValue {
+        let object = js_sys::Object::new();
+        let set = |key: &str, value: JsValue| {
+            // Setting a property of a plain object cannot fail
+            let _ = js_sys::Reflect::set(&object, &JsValue::from_str(key), &value);
+        };
+        let (deck_name, lapses, leeched, memory_state) = match self.card {
+            ContextCard::Review(card) => {
+                set("elapsedDays", JsValue::from(card.elapsed_days));
+                set(
+                    "daysLate",
+                    JsValue::from(card.elapsed_days as i64 as f64 - card.scheduled_days as f64),
+                );
+                set(
+                    "consecutiveSuccessfulReviews",
+                    JsValue::from(card.consecutive_successful_reviews.unwrap_or(0)),
+                );
+                (
+                    &card.deck_name,
+                    card.lapses,
+                    card.leeched,
+                    &card.memory_state,
+                )
+            }
+            // The days since the last review are not known once the card is
+            // relearning, and the lapse already removed the streak
+            ContextCard::Relearning(card) => {
+                set("consecutiveSuccessfulReviews", JsValue::from(0));
+                (
+                    &card.deck_name,
+                    card.lapses,
+                    card.leeched,
+                    &card.memory_state,
+                )
+            }
+        };
+        set("deckName", JsValue::from_str(deck_name));
+        // Numbers in JavaScript cannot represent every u64, but the seed only
+        // needs to vary between cards
+        set("seed", JsValue::from(self.seed as f64));
+        set("lapses", JsValue::from(lapses));
+        set("leeched", JsValue::from(leeched));
+        set(
+            "memoryState",
+            match memory_state {
+                None => JsValue::UNDEFINED,
+                Some(memory_state) => {
+                    let memory_state_object = js_sys::Object::new();
+                    let _ = js_sys::Reflect::set(
+                        &memory_state_object,
+                        &JsValue::from_str("stability"),
+                        &JsValue::from(memory_state.stability),
+                    );
+                    let _ = js_sys::Reflect::set(
+                        &memory_state_object,
+                        &JsValue::from_str("difficulty"),
+                        &JsValue::from(memory_state.difficulty),
+                    );
+                    memory_state_object.into()
+                }
+            },
+        );
+        object.into()
+    }
+}
+
+/// A multiplier can either be a JavaScript function taking in the current ease
+/// factor and the current interval, or a declarative formula that is evaluated
+/// in Rust without calling back into JavaScript.
+pub enum Multiplier {
+    #[cfg(feature = "wasm")]
+    Function(js_sys::Function),
+    Formula(MultiplierFormula),
+}
+
+#[cfg(feature = "wasm")]
+impl<'de> Deserialize<'de> for Multiplier {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value: JsValue = serde_wasm_bindgen::preserve::deserialize(deserializer)?;
+        if value.is_function() {
+            Ok(Multiplier::Function(value.unchecked_into()))
+        } else {
+            serde_wasm_bindgen::from_value(value)
+                .map(Multiplier::Formula)
+                .map_err(|e| D::Error::custom(e.to_string()))
+        }
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+impl<'de> Deserialize<'de> for Multiplier {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        MultiplierFormula::deserialize(deserializer).map(Multiplier::Formula)
+    }
+}
+
+impl Multiplier {
+    pub fn validate(&self, function_name: &'static str) -> Vec<InvalidField> {
+        match self {
+            #[cfg(feature = "wasm")]
+            Multiplier::Function(_) => vec![],
+            Multiplier::Formula(formula) => formula
+                .validate()
+                .map(|message| vec![InvalidField::new(function_name, message)])
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Calculate the multiplier. JavaScript functions are also passed the
+    /// additional arguments and then the context after the current ease factor
+    /// and the current interval, which declarative formulas ignore.
+    #[cfg_attr(not(feature = "wasm"), allow(unused_variables))]
+    pub(crate) fn calculate(
+        &self,
+        function_name: &'static str,
+        current_ease_factor: f64,
+        current_interval: u32,
+        additional_arguments: &[f64],
+        context: Option<&MultiplierContext>,
+    ) -> Result<f64, MultiplierError> {
+        let to_error = |reason| MultiplierError::new(function_name, reason);
+        let multiplier = match self {
+            #[cfg(feature = "wasm")]
+            Multiplier::Function(function) => {
+                let arguments = [current_ease_factor, current_interval as f64]
+                    .into_iter()
+                    .chain(additional_arguments.iter().copied())
+                    .map(JsValue::from)
+                    .collect::<js_sys::Array>();
+                if let Some(context) = context {
+                    arguments.push(&context.to_js_value());
+                }
+                call_function(function, &arguments).map_err(to_error)?
+            }
+            Multiplier::Formula(formula) => {
+                formula.calculate(current_ease_factor, current_interval as f64)
+            }
+        };
+        check_multiplier(multiplier).map_err(to_error)
+    }
+}
+
+/// Call a JavaScript function that is expected to return a number.
+#[cfg(feature = "wasm")]
+pub(crate) fn call_function(
+    function: &js_sys::Function,
+    arguments: &js_sys::Array,
+) -> Result<f64, String> {
+    let value = function.apply(&JsValue::NULL, arguments).map_err(|e| {
+        let message = match e.dyn_ref::<js_sys::Error>() {
+            Some(error) => String::from(error.message()),
+            None => format!("{e:?}"),
+        };
+        format!("threw an exception: {message}")
+    })?;
+    value.as_f64().ok_or_else(|| {
+        format!(
+            "returned {} instead of a number",
+            if value.is_undefined() {
+                "undefined".to_string()
+            } else {
+                format!(
+                    "a value of type {}",
+                    value.js_typeof().as_string().unwrap_or_default()
+                )
+            }
+        )
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MultiplierFormula {
+    /// `a * currentEaseFactor * currentInterval ^ b + c`, which is the shape
+    /// of the approximation of the FSRS parameters.
+    PowerLaw { a: f64, b: f64, c: f64 },
+    /// Linearly interpolates between `[interval, multiplier]` points sorted by
+    /// interval. Intervals outside of the points use the closest point.
+    #[serde(rename_all = "camelCase")]
+    PiecewiseLinear {
+        points: Vec<(f64, f64)>,
+        #[serde(default)]
+        scale_by_ease_factor: bool,
+    },
+    /// Uses the multiplier of the last `[minimumInterval, multiplier]` entry,
+    /// sorted by interval, whose minimum interval has been reached. Intervals
+    /// below the first entry use the first entry.
+    #[serde(rename_all = "camelCase")]
+    LookupTable {
+        entries: Vec<(f64, f64)>,
+        #[serde(default)]
+        scale_by_ease_factor: bool,
+    },
+}
+
+impl MultiplierFormula {
+    /// Return the known fields of a formula of the given type, or `None` if the
+    /// type is not known.
+    pub fn fields(formula_type: &str) -> Option<&'static [&'static str]> {
+        match formula_type {
+            "powerLaw" => Some(&["type", "a", "b", "c"]),
+            "piecewiseLinear" => Some(&["type", "points", "scaleByEaseFactor"]),
+            "lookupTable" => Some(&["type", "entries", "scaleByEaseFactor"]),
+            _ => None,
+        }
+    }
+
+    fn validate(&self) -> Option<String> {
+        let points = match self {
+            MultiplierFormula::PowerLaw { .. } => return None,
+            MultiplierFormula::PiecewiseLinear { points, .. } => points,
+            MultiplierFormula::LookupTable {
+                entries: points, ..
+            } => points,
+        };
+        if points.is_empty() {
+            Some("must have at least one entry".to_string())
+        } else if points.windows(2).any(|window| window[0].0 >= window[1].0) {
+            Some("entries must be sorted by interval without duplicates".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn calculate(&self, current_ease_factor: f64, current_interval: f64) -> f64 {
+        match self {
+            MultiplierFormula::PowerLaw { a, b, c } => {
+                a * current_ease_factor * current_interval.powf(*b) + c
+            }
+            MultiplierFormula::PiecewiseLinear {
+                points,
+                scale_by_ease_factor,
+            } => {
+                let multiplier = piecewise_linear(points, current_interval);
+                scale(multiplier, current_ease_factor, *scale_by_ease_factor)
+            }
+            MultiplierFormula::LookupTable {
+                entries,
+                scale_by_ease_factor,
+            } => {
+                let multiplier = entries
+                    .iter()
+                    .rev()
+                    .find(|(minimum_interval, _)| *minimum_interval <= current_interval)
+                    .or(entries.first())
+                    .map_or(f64::NAN, |(_, multiplier)| *multiplier);
+                scale(multiplier, current_ease_factor, *scale_by_ease_factor)
+            }
+        }
+    }
+}
+
+fn scale(multiplier: f64, current_ease_factor: f64, scale_by_ease_factor: bool) -> f64 {
+    if scale_by_ease_factor {
+        multiplier * current_ease_factor
+    } else {
+        multiplier
+    }
+}
+
+fn piecewise_linear(points: &[(f64, f64)], interval: f64) -> f64 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return f64::NAN;
+    };
+    if interval <= first.0 {
+        return first.1;
+    }
+    if interval >= last.0 {
+        return last.1;
+    }
+    points
+        .windows(2)
+        .find(|window| interval <= window[1].0)
+        .map_or(last.1, |window| {
+            let ((x0, y0), (x1, y1)) = (window[0], window[1]);
+            y0 + (y1 - y0) * (interval - x0) / (x1 - x0)
+        })
+}
+
+/// A multiplier that always returns `multiplier`, regardless of the current
+/// ease factor and the current interval.
+#[cfg(test)]
+pub(crate) fn constant_multiplier(multiplier: f64) -> Multiplier {
+    Multiplier::Formula(MultiplierFormula::PowerLaw {
+        a: 0.0,
+        b: 0.0,
+        c: multiplier,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::multiplier::{check_multiplier, MultiplierFormula};
+    use serde::de::value::{Error, MapDeserializer};
+    use serde::de::{IntoDeserializer, Visitor};
+    use serde::{forward_to_deserialize_any, Deserialize, Deserializer};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    /// A value of a formula field, since the fields of formulas have different
+    /// types.
+    enum FieldValue {
+        Str(&'static str),
+        Number(f64),
+        Bool(bool),
+        Points,
+    }
+
+    impl<'de> IntoDeserializer<'de, Error> for FieldValue {
+        type Deserializer = Self;
+
+        fn into_deserializer(self) -> Self {
+            self
+        }
+    }
+
+    impl<'de> Deserializer<'de> for FieldValue {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self {
+                FieldValue::Str(value) => visitor.visit_borrowed_str(value),
+                FieldValue::Number(value) => visitor.visit_f64(value),
+                FieldValue::Bool(value) => visitor.visit_bool(value),
+                FieldValue::Points => {
+                    IntoDeserializer::<Error>::into_deserializer(vec![vec![1.0, 2.0]])
+                        .deserialize_any(visitor)
+                }
+            }
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn valid_multiplier_is_accepted() {
+        assert_eq!(check_multiplier(0.0), Ok(0.0));
+        assert_eq!(check_multiplier(2.5), Ok(2.5));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn nan_multiplier_is_rejected() {
+        assert_eq!(check_multiplier(f64::NAN), Err("returned NaN".to_string()));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn infinite_multiplier_is_rejected() {
+        assert_eq!(
+            check_multiplier(f64::INFINITY),
+            Err("returned Infinity".to_string())
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn negative_multiplier_is_rejected() {
+        assert_eq!(
+            check_multiplier(-1.5),
+            Err("returned -1.5 which is negative".to_string())
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn power_law_matches_default_good_multiplier() {
+        let under_test = MultiplierFormula::PowerLaw {
+            a: 1.0,
+            b: -0.182458510,
+            c: 1.779479164,
+        };
+
+        let result = under_test.calculate(2.5, 100.0);
+
+        let expected = 2.5 * 100_f64.powf(-0.182458510) + 1.779479164;
+        assert_eq!(result, expected);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn piecewise_linear_interpolates_between_points() {
+        let under_test = MultiplierFormula::PiecewiseLinear {
+            points: vec![(1.0, 3.0), (11.0, 2.0), (101.0, 1.5)],
+            scale_by_ease_factor: false,
+        };
+
+        assert_eq!(under_test.calculate(2.5, 0.0), 3.0);
+        assert_eq!(under_test.calculate(2.5, 6.0), 2.5);
+        assert_eq!(under_test.calculate(2.5, 56.0), 1.75);
+        assert_eq!(under_test.calculate(2.5, 1000.0), 1.5);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn lookup_table_uses_last_reached_entry() {
+        let under_test = MultiplierFormula::LookupTable {
+            entries: vec![(7.0, 2.0), (21.0, 1.5)],
+            scale_by_ease_factor: true,
+        };
+
+        assert_eq!(under_test.calculate(2.0, 1.0), 4.0);
+        assert_eq!(under_test.calculate(2.0, 20.0), 4.0);
+        assert_eq!(under_test.calculate(2.0, 21.0), 3.0);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn unsorted_entries_are_invalid() {
+        let under_test = MultiplierFormula::LookupTable {
+            entries: vec![(21.0, 1.5), (7.0, 2.0)],
+            scale_by_ease_factor: false,
+        };
+
+        let result = under_test.validate();
+
+        assert_eq!(
+            result,
+            Some("entries must be sorted by interval without duplicates".to_string())
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn empty_entries_are_invalid() {
+        let under_test = MultiplierFormula::PiecewiseLinear {
+            points: vec![],
+            scale_by_ease_factor: false,
+        };
+
+        let result = under_test.validate();
+
+        assert_eq!(result, Some("must have at least one entry".to_string()));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fields_depend_on_formula_type() {
+        assert_eq!(
+            MultiplierFormula::fields("powerLaw"),
+            Some(["type", "a", "b", "c"].as_slice())
+        );
+        assert_eq!(
+            MultiplierFormula::fields("lookupTable"),
+            Some(["type", "entries", "scaleByEaseFactor"].as_slice())
+        );
+        assert_eq!(MultiplierFormula::fields("exponential"), None);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn every_known_field_is_read_by_formula() {
+        for formula_type in ["powerLaw", "piecewiseLinear", "lookupTable"] {
+            let deserialize = |fields: &[&'static str]| {
+                MultiplierFormula::deserialize(MapDeserializer::<_, Error>::new(fields.iter().map(
+                    |field| {
+                        let value = match *field {
+                            "type" => FieldValue::Str(formula_type),
+                            "points" | "entries" => FieldValue::Points,
+                            "scaleByEaseFactor" => FieldValue::Bool(true),
+                            _ => FieldValue::Number(2.0),
+                        };
+                        (*field, value)
+                    },
+                )))
+            };
+            let fields = MultiplierFormula::fields(formula_type).unwrap();
+            let formula = deserialize(fields).unwrap();
+
+            // Leaving out a field that is read either fails or changes the
+            // multiplier
+            for field in fields {
+                let other_fields = fields
+                    .iter()
+                    .copied()
+                    .filter(|other_field| other_field != field)
+                    .collect::<Vec<_>>();
+                if let Ok(other_formula) = deserialize(&other_fields) {
+                    assert_ne!(
+                        other_formula.calculate(2.5, 10.0),
+                        formula.calculate(2.5, 10.0),
+                        "{field} of {formula_type} is not read"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,13 @@
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use serde::Deserialize;
-use wasm_bindgen::JsValue;
+
+use crate::card::{Answer, RelearningCard, ReviewCard};
+use crate::fsrs::{Fsrs, MemoryState};
+use crate::leech_policy::LeechPolicy;
+use crate::load_balancer::LoadBalancer;
+use crate::multiplier::{Multiplier, MultiplierContext, MultiplierError};
+use crate::validation::InvalidField;
 
 #[derive(Debug, PartialEq)]
 pub struct NextState {
@@ -10,79 +16,774 @@ pub struct NextState {
     pub easy_interval: Option<u32>,
 }
 
+/// What to do when a multiplier function throws an exception or does not
+/// return a valid multiplier.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MultiplierErrorPolicy {
+    /// Stop the custom scheduler and show the error.
+    #[default]
+    Error,
+    /// Use the native Anki intervals for every button of the card.
+    Anki,
+    /// Use the native Anki interval only for the button whose multiplier
+    /// failed.
+    SkipButton,
+}
+
+/// How the fuzzed interval is picked from the days in the fuzz range.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FuzzDistribution {
+    /// Every day is equally likely, like Anki.
+    #[default]
+    Uniform,
+    /// Days closer to the unfuzzed interval are more likely.
+    Triangular,
+}
+
+impl FuzzDistribution {
+    /// Map a fuzz factor from the range `0.0..1.0` onto the distribution, which
+    /// stays in the same range.
+    fn apply(self, fuzz_factor: f64) -> f64 {
+        match self {
+            FuzzDistribution::Uniform => fuzz_factor,
+            // Inverse of the cumulative distribution function of a triangular
+            // distribution between 0 and 1 with its peak at 0.5
+            FuzzDistribution::Triangular => {
+                if fuzz_factor < 0.5 {
+                    (fuzz_factor / 2.0).sqrt()
+                } else {
+                    1.0 - ((1.0 - fuzz_factor) / 2.0).sqrt()
+                }
+            }
+        }
+    }
+}
+
+/// Replaces how much fuzz Anki applies to review intervals.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FuzzProfile {
+    #[serde(default)]
+    ranges: Option<Vec<FuzzRange>>,
+    #[serde(default = "FuzzProfile::default_strength")]
+    strength: f64,
+    #[serde(default)]
+    distribution: FuzzDistribution,
+}
+
+impl FuzzProfile {
+    pub const FIELDS: &'static [&'static str] = &["ranges", "strength", "distribution"];
+
+    /// Anki's fuzz ranges are used when `ranges` is `None`. The amount of fuzz
+    /// is multiplied by `strength`, where 0 disables fuzz.
+    pub const fn new(
+        ranges: Option<Vec<FuzzRange>>,
+        strength: f64,
+        distribution: FuzzDistribution,
+    ) -> Self {
+        Self {
+            ranges,
+            strength,
+            distribution,
+        }
+    }
+
+    fn default_strength() -> f64 {
+        1.0
+    }
+
+    fn validate(&self) -> Vec<InvalidField> {
+        let mut invalid_fields = Vec::new();
+        for range in self.ranges.iter().flatten() {
+            if !(range.start.is_finite() && range.start >= 0.0) {
+                invalid_fields.push(InvalidField::new(
+                    "fuzzProfile.ranges",
+                    format!("start {} is negative or not a number", range.start),
+                ));
+            }
+            if range.start.is_finite() && (range.end.is_nan() || range.end <= range.start) {
+                invalid_fields.push(InvalidField::new(
+                    "fuzzProfile.ranges",
+                    format!(
+                        "end {} is not greater than start {}",
+                        range.end, range.start
+                    ),
+                ));
+            }
+            if !(range.factor.is_finite() && range.factor >= 0.0) {
+                invalid_fields.push(InvalidField::new(
+                    "fuzzProfile.ranges",
+                    format!("factor {} is negative or not a number", range.factor),
+                ));
+            }
+        }
+        if !(self.strength.is_finite() && self.strength >= 0.0) {
+            invalid_fields.push(InvalidField::new(
+                "fuzzProfile.strength",
+                format!("{} is negative or not a number", self.strength),
+            ));
+        }
+        invalid_fields
+    }
+
+    fn ranges(&self) -> &[FuzzRange] {
+        self.ranges.as_deref().unwrap_or(&FUZZ_RANGES)
+    }
+
+    fn is_disabled(&self) -> bool {
+        self.strength <= 0.0
+    }
+}
+
+/// Anki's fuzz, which is used when no fuzz profile is configured.
+static DEFAULT_FUZZ_PROFILE: FuzzProfile = FuzzProfile::new(None, 1.0, FuzzDistribution::Uniform);
+
+/// Limits how fast the interval of a review card can grow in a single review.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GrowthCeiling {
+    #[serde(default)]
+    maximum_growth_ratio: Option<f64>,
+    #[serde(default)]
+    mature_threshold: Option<u32>,
+}
+
+impl GrowthCeiling {
+    pub const FIELDS: &'static [&'static str] = &["maximumGrowthRatio", "matureThreshold"];
+
+    /// `maximum_growth_ratio` is the most the current interval can be
+    /// multiplied by, and young cards with a current interval below
+    /// `mature_threshold` days cannot grow past it.
+    pub fn new(maximum_growth_ratio: Option<f64>, mature_threshold: Option<u32>) -> Self {
+        Self {
+            maximum_growth_ratio,
+            mature_threshold,
+        }
+    }
+
+    fn validate(&self) -> Vec<InvalidField> {
+        let mut invalid_fields = Vec::new();
+        if let Some(maximum_growth_ratio) = self.maximum_growth_ratio {
+            if !maximum_growth_ratio.is_finite() || maximum_growth_ratio < 1.0 {
+                invalid_fields.push(InvalidField::new(
+                    "growthCeiling.maximumGrowthRatio",
+                    format!("{maximum_growth_ratio} is below 1"),
+                ));
+            }
+        }
+        if self.mature_threshold == Some(0) {
+            invalid_fields.push(InvalidField::new(
+                "growthCeiling.matureThreshold",
+                "0 is below 1".to_string(),
+            ));
+        }
+        invalid_fields
+    }
+
+    /// Return the longest interval a card with the current interval can get,
+    /// or `None` if it is not limited.
+    fn maximum_interval(&self, scheduled_days: u32) -> Option<u32> {
+        let current_interval = scheduled_days.max(1);
+        let growth_ceiling = self.maximum_growth_ratio.map(|maximum_growth_ratio| {
+            (current_interval as f64 * maximum_growth_ratio.max(1.0)).floor() as u32
+        });
+        let mature_ceiling = self
+            .mature_threshold
+            .filter(|mature_threshold| current_interval < *mature_threshold);
+        growth_ceiling.into_iter().chain(mature_ceiling).min()
+    }
+}
+
+/// Derive the intervals of cards with an FSRS memory state from their stability
+/// instead of the multipliers, while the ease factor keeps being updated.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FsrsHybrid {
+    desired_retention: f64,
+    #[serde(default)]
+    parameters: Option<[f64; 21]>,
+}
+
+impl FsrsHybrid {
+    pub const FIELDS: &'static [&'static str] = &["desiredRetention", "parameters"];
+
+    /// The default FSRS-6 parameters are used when `parameters` is `None`.
+    pub fn new(desired_retention: f64, parameters: Option<[f64; 21]>) -> Self {
+        Self {
+            desired_retention,
+            parameters,
+        }
+    }
+
+    fn validate(&self) -> Vec<InvalidField> {
+        let mut invalid_fields = Vec::new();
+        if !(self.desired_retention > 0.0 && self.desired_retention < 1.0) {
+            invalid_fields.push(InvalidField::new(
+                "fsrsHybrid.desiredRetention",
+                format!("{} is not between 0 and 1", self.desired_retention),
+            ));
+        }
+        if self
+            .parameters
+            .is_some_and(|parameters| parameters.iter().any(|parameter| !parameter.is_finite()))
+        {
+            invalid_fields.push(InvalidField::new(
+                "fsrsHybrid.parameters",
+                "must only contain numbers".to_string(),
+            ));
+        }
+        invalid_fields
+    }
+
+    /// Return the unrounded hard, good, and easy intervals, where each interval
+    /// is the time until the retrievability of the card after pressing the
+    /// button drops to the desired retention.
+    fn next_intervals(&self, memory_state: MemoryState, elapsed_days: u32) -> (f64, f64, f64) {
+        let fsrs = self.parameters.map_or_else(Fsrs::default, Fsrs::new);
+        let next_interval = |answer| {
+            let next_memory_state =
+                fsrs.next_memory_state(memory_state, elapsed_days as f64, answer);
+            fsrs.next_interval(next_memory_state.stability, self.desired_retention)
+        };
+        (
+            next_interval(Answer::Hard),
+            next_interval(Answer::Good),
+            next_interval(Answer::Easy),
+        )
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Scheduler {
     enable_fuzz: bool,
+    #[serde(default)]
+    enable_early_review: bool,
     maximum_interval: u32,
     interval_modifier: f64,
-    #[serde(with = "serde_wasm_bindgen::preserve")]
-    calculate_hard_multiplier: js_sys::Function,
-    #[serde(with = "serde_wasm_bindgen::preserve")]
-    calculate_good_multiplier: js_sys::Function,
-    #[serde(with = "serde_wasm_bindgen::preserve")]
-    calculate_easy_multiplier: js_sys::Function,
+    calculate_hard_multiplier: Multiplier,
+    calculate_good_multiplier: Multiplier,
+    calculate_easy_multiplier: Multiplier,
+    #[serde(default)]
+    on_multiplier_error: MultiplierErrorPolicy,
+    #[serde(default)]
+    fsrs_hybrid: Option<FsrsHybrid>,
+    #[serde(default)]
+    easy_days: Option<[f64; 7]>,
+    #[serde(default)]
+    fuzz_profile: Option<FuzzProfile>,
+    #[serde(default)]
+    hard_interval_modifier: Option<f64>,
+    #[serde(default)]
+    good_interval_modifier: Option<f64>,
+    #[serde(default)]
+    easy_interval_modifier: Option<f64>,
+    #[serde(default)]
+    hard_maximum_interval: Option<u32>,
+    #[serde(default)]
+    good_maximum_interval: Option<u32>,
+    #[serde(default)]
+    easy_maximum_interval: Option<u32>,
+    #[serde(default)]
+    hard_minimum_gap: Option<u32>,
+    #[serde(default)]
+    good_minimum_gap: Option<u32>,
+    #[serde(default)]
+    easy_minimum_gap: Option<u32>,
+    #[serde(default)]
+    growth_ceiling: Option<GrowthCeiling>,
 }
 
 impl Scheduler {
+    pub const FIELDS: &'static [&'static str] = &[
+        "enableFuzz",
+        "enableEarlyReview",
+        "maximumInterval",
+        "intervalModifier",
+        "calculateHardMultiplier",
+        "calculateGoodMultiplier",
+        "calculateEasyMultiplier",
+        "onMultiplierError",
+        "fsrsHybrid",
+        "easyDays",
+        "fuzzProfile",
+        "hardIntervalModifier",
+        "goodIntervalModifier",
+        "easyIntervalModifier",
+        "hardMaximumInterval",
+        "goodMaximumInterval",
+        "easyMaximumInterval",
+        "hardMinimumGap",
+        "goodMinimumGap",
+        "easyMinimumGap",
+        "growthCeiling",
+    ];
+
+    pub fn new(
+        enable_fuzz: bool,
+        maximum_interval: u32,
+        interval_modifier: f64,
+        calculate_hard_multiplier: Multiplier,
+        calculate_good_multiplier: Multiplier,
+        calculate_easy_multiplier: Multiplier,
+    ) -> Self {
+        Self {
+            enable_fuzz,
+            enable_early_review: false,
+            maximum_interval,
+            interval_modifier,
+            calculate_hard_multiplier,
+            calculate_good_multiplier,
+            calculate_easy_multiplier,
+            on_multiplier_error: MultiplierErrorPolicy::default(),
+            fsrs_hybrid: None,
+            easy_days: None,
+            fuzz_profile: None,
+            hard_interval_modifier: None,
+            good_interval_modifier: None,
+            easy_interval_modifier: None,
+            hard_maximum_interval: None,
+            good_maximum_interval: None,
+            easy_maximum_interval: None,
+            hard_minimum_gap: None,
+            good_minimum_gap: None,
+            easy_minimum_gap: None,
+            growth_ceiling: None,
+        }
+    }
+
+    /// Derive the intervals of cards with an FSRS memory state from their
+    /// stability instead of the multipliers.
+    pub fn with_fsrs_hybrid(mut self, fsrs_hybrid: FsrsHybrid) -> Self {
+        self.fsrs_hybrid = Some(fsrs_hybrid);
+        self
+    }
+
+    /// Weigh the workload of each day of the week, starting from Sunday, where
+    /// 1 is a normal day and 0 is a day without reviews. Fuzzed intervals are
+    /// biased towards days with a higher weight.
+    ///
+    /// The day of the week of today is not known to the scheduler, and is
+    /// taken from the load balancer passed to [`Scheduler::next_states`]
+    /// instead, see [`LoadBalancer::with_weekday`]. Easy days have no effect
+    /// without a load balancer with a weekday, or when fuzz is disabled, since
+    /// the interval is picked from the fuzz range.
+    pub fn with_easy_days(mut self, easy_days: [f64; 7]) -> Self {
+        self.easy_days = Some(easy_days);
+        self
+    }
+
+    /// Replace how much fuzz is applied to review intervals, and how the
+    /// fuzzed interval is picked.
+    pub fn with_fuzz_profile(mut self, fuzz_profile: FuzzProfile) -> Self {
+        self.fuzz_profile = Some(fuzz_profile);
+        self
+    }
+
+    /// Override the interval modifier of the hard, good, and easy buttons. The
+    /// interval modifier is used for buttons that are `None`.
+    pub fn with_interval_modifiers(
+        mut self,
+        hard: Option<f64>,
+        good: Option<f64>,
+        easy: Option<f64>,
+    ) -> Self {
+        self.hard_interval_modifier = hard;
+        self.good_interval_modifier = good;
+        self.easy_interval_modifier = easy;
+        self
+    }
+
+    /// Override the maximum interval of the hard, good, and easy buttons. The
+    /// maximum interval is used for buttons that are `None`.
+    pub fn with_maximum_intervals(
+        mut self,
+        hard: Option<u32>,
+        good: Option<u32>,
+        easy: Option<u32>,
+    ) -> Self {
+        self.hard_maximum_interval = hard;
+        self.good_maximum_interval = good;
+        self.easy_maximum_interval = easy;
+        self
+    }
+
+    /// Set the minimum number of days the interval of the hard, good, and easy
+    /// buttons is longer than the interval of the button before it, where the
+    /// button before hard is the current interval. Buttons that are `None` use
+    /// 1 day like Anki.
+    pub fn with_minimum_gaps(
+        mut self,
+        hard: Option<u32>,
+        good: Option<u32>,
+        easy: Option<u32>,
+    ) -> Self {
+        self.hard_minimum_gap = hard;
+        self.good_minimum_gap = good;
+        self.easy_minimum_gap = easy;
+        self
+    }
+
+    /// Limit how fast the interval of review cards can grow, on top of the
+    /// maximum intervals.
+    pub fn with_growth_ceiling(mut self, growth_ceiling: GrowthCeiling) -> Self {
+        self.growth_ceiling = Some(growth_ceiling);
+        self
+    }
+
+    /// Return the overrides of each button, where the interval modifier and
+    /// maximum interval are adjusted by the leech policy like the shared ones.
+    fn button_overrides(
+        &self,
+        leech_policy: Option<&LeechPolicy>,
+        lapses: u32,
+        leeched: bool,
+    ) -> ButtonOverrides {
+        let button_override =
+            |interval_modifier: Option<f64>,
+             maximum_interval: Option<u32>,
+             minimum_gap: Option<u32>| ButtonOverride {
+                interval_modifier: interval_modifier.map(|interval_modifier| {
+                    leech_policy.map_or(interval_modifier, |leech_policy| {
+                        leech_policy.interval_modifier(interval_modifier, lapses)
+                    })
+                }),
+                maximum_interval: maximum_interval.map(|maximum_interval| {
+                    leech_policy.map_or(maximum_interval, |leech_policy| {
+                        leech_policy.maximum_interval(maximum_interval, lapses, leeched)
+                    })
+                }),
+                minimum_gap,
+            };
+        ButtonOverrides {
+            hard: button_override(
+                self.hard_interval_modifier,
+                self.hard_maximum_interval,
+                self.hard_minimum_gap,
+            ),
+            good: button_override(
+                self.good_interval_modifier,
+                self.good_maximum_interval,
+                self.good_minimum_gap,
+            ),
+            easy: button_override(
+                self.easy_interval_modifier,
+                self.easy_maximum_interval,
+                self.easy_minimum_gap,
+            ),
+        }
+    }
+
+    pub fn validate(&self) -> Vec<InvalidField> {
+        let mut invalid_fields = Vec::new();
+        if self.maximum_interval == 0 {
+            invalid_fields.push(InvalidField::new(
+                "maximumInterval",
+                "0 is below 1".to_string(),
+            ));
+        }
+        if !self.interval_modifier.is_finite() || self.interval_modifier <= 0.0 {
+            invalid_fields.push(InvalidField::new(
+                "intervalModifier",
+                format!("{} is not greater than 0", self.interval_modifier),
+            ));
+        }
+        for (field, interval_modifier) in [
+            ("hardIntervalModifier", self.hard_interval_modifier),
+            ("goodIntervalModifier", self.good_interval_modifier),
+            ("easyIntervalModifier", self.easy_interval_modifier),
+        ] {
+            if let Some(interval_modifier) = interval_modifier {
+                if !interval_modifier.is_finite() || interval_modifier <= 0.0 {
+                    invalid_fields.push(InvalidField::new(
+                        field,
+                        format!("{interval_modifier} is not greater than 0"),
+                    ));
+                }
+            }
+        }
+        for (field, maximum_interval) in [
+            ("hardMaximumInterval", self.hard_maximum_interval),
+            ("goodMaximumInterval", self.good_maximum_interval),
+            ("easyMaximumInterval", self.easy_maximum_interval),
+        ] {
+            if maximum_interval == Some(0) {
+                invalid_fields.push(InvalidField::new(field, "0 is below 1".to_string()));
+            }
+        }
+        invalid_fields.extend(
+            self.calculate_hard_multiplier
+                .validate("calculateHardMultiplier"),
+        );
+        invalid_fields.extend(
+            self.calculate_good_multiplier
+                .validate("calculateGoodMultiplier"),
+        );
+        invalid_fields.extend(
+            self.calculate_easy_multiplier
+                .validate("calculateEasyMultiplier"),
+        );
+        if let Some(fsrs_hybrid) = &self.fsrs_hybrid {
+            invalid_fields.extend(fsrs_hybrid.validate());
+        }
+        if let Some(fuzz_profile) = &self.fuzz_profile {
+            invalid_fields.extend(fuzz_profile.validate());
+        }
+        if let Some(growth_ceiling) = &self.growth_ceiling {
+            invalid_fields.extend(growth_ceiling.validate());
+        }
+        if let Some(easy_days) = &self.easy_days {
+            invalid_fields.extend(
+                easy_days
+                    .iter()
+                    .filter(|weight| !(0.0..=1.0).contains(*weight))
+                    .map(|weight| {
+                        InvalidField::new("easyDays", format!("{weight} is not between 0 and 1"))
+                    }),
+            );
+        }
+        invalid_fields
+    }
+
+    /// Calculate the intervals of a review card. The multiplier functions are
+    /// also passed the number of lapses, whether the card is leeched, as 1 or
+    /// 0, and the card as a context object. With the FSRS hybrid mode,
+    /// cards with a memory state use FSRS instead of the multipliers.
     pub fn next_states(
         &self,
         fuzz_seed: u64,
-        scheduled_days: u32,
-        elapsed_days: u32,
-        ease_factor: f64,
-    ) -> NextState {
-        let hard_multiplier = self.calculate_hard_multiplier(ease_factor, scheduled_days);
-        let good_multiplier = self.calculate_good_multiplier(ease_factor, scheduled_days);
-        let easy_multiplier = self.calculate_easy_multiplier(ease_factor, scheduled_days);
+        card: &ReviewCard,
+        leech_policy: Option<&LeechPolicy>,
+        load_balancer: Option<&LoadBalancer>,
+    ) -> Result<NextState, MultiplierError> {
+        let maximum_interval = leech_policy.map_or(self.maximum_interval, |leech_policy| {
+            leech_policy.maximum_interval(self.maximum_interval, card.lapses, card.leeched)
+        });
+        let interval_ceiling = self
+            .growth_ceiling
+            .as_ref()
+            .and_then(|growth_ceiling| growth_ceiling.maximum_interval(card.scheduled_days));
+        if let (Some(fsrs_hybrid), Some(memory_state)) = (&self.fsrs_hybrid, card.memory_state) {
+            // Like Anki, the interval modifier is not applied to FSRS intervals
+            let scheduler = InternalScheduler::new(
+                self.enable_fuzz,
+                self.enable_early_review,
+                maximum_interval,
+                1.0,
+                0.0,
+                0.0,
+                0.0,
+                fuzz_seed,
+                card.scheduled_days,
+                card.elapsed_days,
+            )
+            .with_load_balancer(load_balancer)
+            .with_easy_days(self.easy_days.as_ref())
+            .with_fuzz_profile(self.fuzz_profile.as_ref())
+            .with_button_overrides(
+                self.button_overrides(leech_policy, card.lapses, card.leeched)
+                    .without_interval_modifiers(),
+            )
+            .with_interval_ceiling(interval_ceiling);
+            let (hard_interval, good_interval, easy_interval) = scheduler
+                .passing_fsrs_review_intervals(
+                    fsrs_hybrid.next_intervals(memory_state, card.elapsed_days),
+                );
+            return Ok(NextState {
+                hard_interval: Some(hard_interval),
+                good_interval: Some(good_interval),
+                easy_interval: Some(easy_interval),
+            });
+        }
+
+        let additional_arguments = [card.lapses as f64, u8::from(card.leeched) as f64];
+        let context = MultiplierContext::new(card, fuzz_seed);
+        let mut multipliers = [0.0; 3];
+        for (multiplier, (function_name, calculate_multiplier)) in multipliers.iter_mut().zip([
+            ("calculateHardMultiplier", &self.calculate_hard_multiplier),
+            ("calculateGoodMultiplier", &self.calculate_good_multiplier),
+            ("calculateEasyMultiplier", &self.calculate_easy_multiplier),
+        ]) {
+            match calculate_multiplier.calculate(
+                function_name,
+                card.ease_factor,
+                card.scheduled_days,
+                &additional_arguments,
+                Some(&context),
+            ) {
+                Ok(value) => *multiplier = value,
+                Err(e) => match self.on_multiplier_error {
+                    MultiplierErrorPolicy::Error => return Err(e),
+                    MultiplierErrorPolicy::Anki => {
+                        return Ok(NextState {
+                            hard_interval: None,
+                            good_interval: None,
+                            easy_interval: None,
+                        })
+                    }
+                    // A multiplier of 0 falls back to the native Anki interval
+                    MultiplierErrorPolicy::SkipButton => *multiplier = 0.0,
+                },
+            }
+        }
+        let [hard_multiplier, good_multiplier, easy_multiplier] = multipliers;
+
+        let interval_modifier = leech_policy.map_or(self.interval_modifier, |leech_policy| {
+            leech_policy.interval_modifier(self.interval_modifier, card.lapses)
+        });
         let scheduler = InternalScheduler::new(
             self.enable_fuzz,
-            self.maximum_interval,
-            self.interval_modifier,
+            self.enable_early_review,
+            maximum_interval,
+            interval_modifier,
             hard_multiplier,
             good_multiplier,
             easy_multiplier,
             fuzz_seed,
-            scheduled_days,
-            elapsed_days,
-        );
-        scheduler.next_states()
+            card.scheduled_days,
+            card.elapsed_days,
+        )
+        .with_load_balancer(load_balancer)
+        .with_easy_days(self.easy_days.as_ref())
+        .with_fuzz_profile(self.fuzz_profile.as_ref())
+        .with_button_overrides(self.button_overrides(leech_policy, card.lapses, card.leeched))
+        .with_interval_ceiling(interval_ceiling);
+        Ok(scheduler.next_states())
     }
 
-    fn calculate_hard_multiplier(&self, current_ease_factor: f64, current_interval: u32) -> f64 {
-        self.calculate_hard_multiplier
-            .call2(
-                &JsValue::NULL,
-                &JsValue::from(current_ease_factor),
-                &JsValue::from(current_interval),
-            )
-            .unwrap()
-            .as_f64()
-            .expect("The calculateHardMultiplier JavaScript function should return a number")
+    /// Return the intervals of a relearning card graduating back to review by
+    /// pressing Good or Easy, where the interval is the lapse multiplier times
+    /// the interval before the lapse. The maximum interval and the interval
+    /// modifier are adjusted by the leech policy like for review cards, and the
+    /// growth ceiling limits the growth from the interval before the lapse. A
+    /// lapse multiplier of 0 leaves the intervals to Anki.
+    pub fn relearning_graduation_states(
+        &self,
+        fuzz_seed: u64,
+        card: &RelearningCard,
+        lapse_multiplier: f64,
+        leech_policy: Option<&LeechPolicy>,
+        load_balancer: Option<&LoadBalancer>,
+    ) -> NextState {
+        if lapse_multiplier == 0.0 {
+            return NextState {
+                hard_interval: None,
+                good_interval: None,
+                easy_interval: None,
+            };
+        }
+        let maximum_interval = leech_policy.map_or(self.maximum_interval, |leech_policy| {
+            leech_policy.maximum_interval(self.maximum_interval, card.lapses, card.leeched)
+        });
+        let interval_modifier = leech_policy.map_or(self.interval_modifier, |leech_policy| {
+            leech_policy.interval_modifier(self.interval_modifier, card.lapses)
+        });
+        let interval_ceiling = self
+            .growth_ceiling
+            .as_ref()
+            .and_then(|growth_ceiling| growth_ceiling.maximum_interval(card.previous_interval));
+        let scheduler = InternalScheduler::new(
+            self.enable_fuzz,
+            self.enable_early_review,
+            maximum_interval,
+            interval_modifier,
+            0.0,
+            lapse_multiplier,
+            lapse_multiplier,
+            fuzz_seed,
+            card.previous_interval,
+            card.previous_interval,
+        )
+        .with_load_balancer(load_balancer)
+        .with_easy_days(self.easy_days.as_ref())
+        .with_fuzz_profile(self.fuzz_profile.as_ref())
+        .with_button_overrides(self.button_overrides(leech_policy, card.lapses, card.leeched))
+        .with_interval_ceiling(interval_ceiling);
+        let (good_interval, easy_interval) = scheduler.relearning_graduation_intervals();
+        NextState {
+            hard_interval: None,
+            good_interval: Some(good_interval),
+            easy_interval: Some(easy_interval),
+        }
     }
 
-    fn calculate_good_multiplier(&self, current_ease_factor: f64, current_interval: u32) -> f64 {
-        self.calculate_good_multiplier
-            .call2(
-                &JsValue::NULL,
-                &JsValue::from(current_ease_factor),
-                &JsValue::from(current_interval),
-            )
-            .unwrap()
-            .as_f64()
-            .expect("The calculateGoodMultiplier JavaScript function should return a number")
+    /// Return the intervals of a learning card graduating to review by pressing
+    /// Good on the last learning step or Easy. Like Anki, the intervals are
+    /// fuzzed but not affected by the interval modifier. An interval that is
+    /// `None` or 0 is left to Anki.
+    pub fn learning_graduation_states(
+        &self,
+        fuzz_seed: u64,
+        graduating_interval: Option<f64>,
+        easy_interval: Option<f64>,
+        load_balancer: Option<&LoadBalancer>,
+    ) -> NextState {
+        let scheduler = InternalScheduler::new(
+            self.enable_fuzz,
+            self.enable_early_review,
+            self.maximum_interval,
+            1.0,
+            0.0,
+            0.0,
+            0.0,
+            fuzz_seed,
+            0,
+            0,
+        )
+        .with_load_balancer(load_balancer)
+        .with_easy_days(self.easy_days.as_ref())
+        .with_fuzz_profile(self.fuzz_profile.as_ref())
+        .with_button_overrides(
+            self.button_overrides(None, 0, false)
+                .without_interval_modifiers(),
+        );
+        let (good_interval, easy_interval) = scheduler.learning_graduation_intervals(
+            graduating_interval.filter(|interval| *interval != 0.0),
+            easy_interval.filter(|interval| *interval != 0.0),
+        );
+        NextState {
+            hard_interval: None,
+            good_interval,
+            easy_interval,
+        }
     }
+}
 
-    fn calculate_easy_multiplier(&self, current_ease_factor: f64, current_interval: u32) -> f64 {
-        self.calculate_easy_multiplier
-            .call2(
-                &JsValue::NULL,
-                &JsValue::from(current_ease_factor),
-                &JsValue::from(current_interval),
-            )
-            .unwrap()
-            .as_f64()
-            .expect("The calculateEasyMultiplier JavaScript function should return a number")
+/// The interval modifier, maximum interval, and minimum gap of a button that
+/// are used instead of the ones shared by every button.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ButtonOverride {
+    interval_modifier: Option<f64>,
+    maximum_interval: Option<u32>,
+    minimum_gap: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ButtonOverrides {
+    hard: ButtonOverride,
+    good: ButtonOverride,
+    easy: ButtonOverride,
+}
+
+impl ButtonOverrides {
+    fn get(&self, answer: Answer) -> ButtonOverride {
+        match answer {
+            Answer::Again => ButtonOverride::default(),
+            Answer::Hard => self.hard,
+            Answer::Good => self.good,
+            Answer::Easy => self.easy,
+        }
+    }
+
+    /// Like Anki, the interval modifier is not applied to FSRS and learning
+    /// intervals, so neither are the overrides.
+    fn without_interval_modifiers(mut self) -> Self {
+        for button_override in [&mut self.hard, &mut self.good, &mut self.easy] {
+            button_override.interval_modifier = None;
+        }
+        self
     }
 }
 
@@ -98,8 +799,14 @@ impl Scheduler {
 //
 // See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/scheduler/states/review.rs
 
-struct InternalScheduler {
+struct InternalScheduler<'a> {
     fuzz_factor: Option<f64>,
+    load_balancer: Option<&'a LoadBalancer>,
+    easy_days: Option<&'a [f64; 7]>,
+    fuzz_profile: &'a FuzzProfile,
+    button_overrides: ButtonOverrides,
+    interval_ceiling: Option<u32>,
+    enable_early_review: bool,
     maximum_interval: u32,
     interval_modifier: f64,
     hard_multiplier: f64,
@@ -109,10 +816,11 @@ struct InternalScheduler {
     elapsed_days: u32,
 }
 
-impl InternalScheduler {
+impl<'a> InternalScheduler<'a> {
     #[allow(clippy::too_many_arguments)]
     fn new(
         enable_fuzz: bool,
+        enable_early_review: bool,
         maximum_interval: u32,
         interval_modifier: f64,
         hard_multiplier: f64,
@@ -129,6 +837,12 @@ impl InternalScheduler {
         };
         Self {
             fuzz_factor,
+            load_balancer: None,
+            easy_days: None,
+            fuzz_profile: &DEFAULT_FUZZ_PROFILE,
+            button_overrides: ButtonOverrides::default(),
+            interval_ceiling: None,
+            enable_early_review,
             maximum_interval,
             interval_modifier,
             hard_multiplier,
@@ -139,6 +853,62 @@ impl InternalScheduler {
         }
     }
 
+    fn with_load_balancer(mut self, load_balancer: Option<&'a LoadBalancer>) -> Self {
+        self.load_balancer = load_balancer;
+        self
+    }
+
+    fn with_easy_days(mut self, easy_days: Option<&'a [f64; 7]>) -> Self {
+        self.easy_days = easy_days;
+        self
+    }
+
+    fn with_fuzz_profile(mut self, fuzz_profile: Option<&'a FuzzProfile>) -> Self {
+        if let Some(fuzz_profile) = fuzz_profile {
+            if fuzz_profile.is_disabled() {
+                self.fuzz_factor = None;
+            }
+            self.fuzz_profile = fuzz_profile;
+        }
+        self
+    }
+
+    fn with_button_overrides(mut self, button_overrides: ButtonOverrides) -> Self {
+        self.button_overrides = button_overrides;
+        self
+    }
+
+    /// Cap the maximum interval of every button, including the overrides.
+    fn with_interval_ceiling(mut self, interval_ceiling: Option<u32>) -> Self {
+        self.interval_ceiling = interval_ceiling;
+        self
+    }
+
+    fn interval_modifier(&self, answer: Answer) -> f64 {
+        self.button_overrides
+            .get(answer)
+            .interval_modifier
+            .unwrap_or(self.interval_modifier)
+    }
+
+    fn maximum_interval(&self, answer: Answer) -> u32 {
+        let maximum_interval = self
+            .button_overrides
+            .get(answer)
+            .maximum_interval
+            .unwrap_or(self.maximum_interval);
+        self.interval_ceiling
+            .map_or(maximum_interval, |interval_ceiling| {
+                maximum_interval.min(interval_ceiling)
+            })
+    }
+
+    /// The minimum number of days the interval of the button is longer than
+    /// the interval of the button before it, which is 1 in Anki.
+    fn minimum_gap(&self, answer: Answer) -> u32 {
+        self.button_overrides.get(answer).minimum_gap.unwrap_or(1)
+    }
+
     fn next_states(&self) -> NextState {
         match self.passing_review_intervals() {
             None => NextState {
@@ -175,12 +945,14 @@ impl InternalScheduler {
         //    self.passing_fsrs_review_intervals(ctx, states)
         // } else if self.days_late() < 0 {
         if self.days_late() < 0 {
-            // Use original Anki algorithm for early reviews for now because it
-            // might not make sense to use the same multipliers as non-early
-            // reviews
-            //
-            // self.passing_early_review_intervals()
-            None
+            // Use original Anki algorithm for early reviews unless explicitly
+            // enabled because it might not make sense to use the same
+            // multipliers as non-early reviews
+            if self.enable_early_review {
+                Some(self.passing_early_review_intervals())
+            } else {
+                None
+            }
         } else {
             Some(self.passing_nonearly_review_intervals())
         }
@@ -192,20 +964,26 @@ impl InternalScheduler {
 
         // hard
         let hard_factor = self.hard_multiplier;
+        // Anki original implementation uses a minimum gap of 1 for every button
         let hard_minimum = if hard_factor <= 1.0 {
             0
         } else {
-            self.scheduled_days + 1
+            self.scheduled_days + self.minimum_gap(Answer::Hard)
         };
-        let hard_interval =
-            self.constrain_passing_interval(current_interval * hard_factor, hard_minimum, true);
+        let hard_interval = self.constrain_passing_interval(
+            Answer::Hard,
+            current_interval * hard_factor,
+            hard_minimum,
+            true,
+        );
         // good
         let good_minimum = if hard_factor <= 1.0 {
-            self.scheduled_days + 1
+            self.scheduled_days + self.minimum_gap(Answer::Good)
         } else {
-            hard_interval + 1
+            hard_interval + self.minimum_gap(Answer::Good)
         };
         let good_interval = self.constrain_passing_interval(
+            Answer::Good,
             // Anki original implementation
             // (current_interval + days_late / 2.0) * self.ease_factor,
             (current_interval + days_late / 2.0) * self.good_multiplier,
@@ -214,29 +992,136 @@ impl InternalScheduler {
         );
         // easy
         let easy_interval = self.constrain_passing_interval(
+            Answer::Easy,
             // Anki original implementation
             // (current_interval + days_late) * self.ease_factor * self.easy_multiplier,
             (current_interval + days_late) * self.easy_multiplier,
-            good_interval + 1,
+            good_interval + self.minimum_gap(Answer::Easy),
+            true,
+        );
+
+        (hard_interval, good_interval, easy_interval)
+    }
+
+    // See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/scheduler/states/review.rs
+    fn passing_fsrs_review_intervals(&self, intervals: (f64, f64, f64)) -> (u32, u32, u32) {
+        let (hard, good, easy) = intervals;
+        let hard_interval = self.constrain_passing_interval(Answer::Hard, hard, 1, true);
+        let good_interval = self.constrain_passing_interval(
+            Answer::Good,
+            good,
+            hard_interval + self.minimum_gap(Answer::Good),
+            true,
+        );
+        let easy_interval = self.constrain_passing_interval(
+            Answer::Easy,
+            easy,
+            good_interval + self.minimum_gap(Answer::Easy),
+            true,
+        );
+        (hard_interval, good_interval, easy_interval)
+    }
+
+    // Unlike Anki, which uses the interval that was set when the card lapsed,
+    // the lapse multiplier is applied to the interval before the lapse, which
+    // then goes through the interval modifier, the per-button overrides of Good
+    // and Easy, and fuzz the same way as a passing review
+    // See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/scheduler/states/relearning.rs
+    fn relearning_graduation_intervals(&self) -> (u32, u32) {
+        let interval = (self.scheduled_days as f64).max(1.0) * self.good_multiplier;
+        let good_interval = self.constrain_passing_interval(Answer::Good, interval, 1, true);
+        let easy_interval = self.constrain_passing_interval(
+            Answer::Easy,
+            interval,
+            good_interval + self.minimum_gap(Answer::Easy),
             true,
         );
+        (good_interval, easy_interval)
+    }
+
+    // See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/scheduler/states/learning.rs
+    fn learning_graduation_intervals(
+        &self,
+        graduating_interval: Option<f64>,
+        easy_interval: Option<f64>,
+    ) -> (Option<u32>, Option<u32>) {
+        let (minimum, maximum) = self.min_and_max_review_intervals(Answer::Good, 1);
+        let good_interval =
+            graduating_interval.map(|interval| self.with_review_fuzz(interval, minimum, maximum));
+        let easy_interval = easy_interval.map(|interval| {
+            let (minimum, maximum) = self.min_and_max_review_intervals(
+                Answer::Easy,
+                good_interval.map_or(1, |good| good + self.minimum_gap(Answer::Easy)),
+            );
+            self.with_review_fuzz(interval, minimum, maximum)
+        });
+        (good_interval, easy_interval)
+    }
+
+    fn passing_early_review_intervals(&self) -> (u32, u32, u32) {
+        let scheduled = self.scheduled_days as f64;
+        let elapsed = (self.scheduled_days as f64) + (self.days_late() as f64);
+
+        let hard_interval = {
+            let factor = self.hard_multiplier;
+            let half_usual = factor / 2.0;
+            self.constrain_passing_interval(
+                Answer::Hard,
+                (elapsed * factor).max(scheduled * half_usual),
+                0,
+                false,
+            )
+        };
+
+        let good_interval = self.constrain_passing_interval(
+            Answer::Good,
+            // Anki original implementation
+            // (elapsed * self.ease_factor).max(scheduled),
+            (elapsed * self.good_multiplier).max(scheduled),
+            0,
+            false,
+        );
+
+        let easy_interval = {
+            // Anki original implementation
+            // let easy_mult = ctx.easy_multiplier as f64;
+            // let reduced_bonus = easy_mult - (easy_mult - 1.0) / 2.0;
+            // (elapsed * self.ease_factor).max(scheduled) * reduced_bonus as f32,
+            //
+            // Our easy multiplier already includes the ease factor, so the
+            // easy bonus is the ratio between the easy and good multipliers
+            let interval = if self.good_multiplier == 0.0 {
+                (elapsed * self.easy_multiplier).max(scheduled)
+            } else {
+                let easy_bonus = self.easy_multiplier / self.good_multiplier;
+                let reduced_bonus = easy_bonus - (easy_bonus - 1.0) / 2.0;
+                (elapsed * self.good_multiplier).max(scheduled) * reduced_bonus
+            };
+            self.constrain_passing_interval(Answer::Easy, interval, 0, false)
+        };
 
         (hard_interval, good_interval, easy_interval)
     }
 
     /// Transform the provided hard/good/easy interval.
-    /// - Apply configured interval multiplier if not FSRS.
+    /// - Apply configured interval multiplier of the button if not FSRS.
     /// - Apply fuzz.
     /// - Ensure it is at least `minimum`, and at least 1.
-    /// - Ensure it is at or below the configured maximum interval.
-    fn constrain_passing_interval(&self, interval: f64, minimum: u32, fuzz: bool) -> u32 {
+    /// - Ensure it is at or below the configured maximum interval of the button.
+    fn constrain_passing_interval(
+        &self,
+        answer: Answer,
+        interval: f64,
+        minimum: u32,
+        fuzz: bool,
+    ) -> u32 {
         // let interval = if ctx.fsrs_next_states.is_some() {
         //     interval
         // } else {
         //     interval * ctx.interval_multiplier
         // };
-        let interval = interval * self.interval_modifier;
-        let (minimum, maximum) = self.min_and_max_review_intervals(minimum);
+        let interval = interval * self.interval_modifier(answer);
+        let (minimum, maximum) = self.min_and_max_review_intervals(answer, minimum);
         if fuzz {
             self.with_review_fuzz(interval, minimum, maximum)
         } else {
@@ -246,30 +1131,60 @@ impl InternalScheduler {
 
     // See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/scheduler/states/mod.rs#L121
     /// Return the minimum and maximum review intervals.
-    /// - `maximum` is `self.maximum_review_interval`, but at least 1.
+    /// - `maximum` is the maximum interval of the button, but at least 1.
     /// - `minimum` is as passed, but at least 1, and at most `maximum`.
-    fn min_and_max_review_intervals(&self, minimum: u32) -> (u32, u32) {
-        let maximum = self.maximum_interval.max(1);
+    fn min_and_max_review_intervals(&self, answer: Answer, minimum: u32) -> (u32, u32) {
+        let maximum = self.maximum_interval(answer).max(1);
         let minimum = minimum.clamp(1, maximum);
         (minimum, maximum)
     }
 
     // See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/scheduler/states/fuzz.rs#L36
     fn with_review_fuzz(&self, interval: f64, minimum: u32, maximum: u32) -> u32 {
+        // Anki original implementation
         // self.load_balancer_ctx
         //     .as_ref()
         //     .and_then(|load_balancer_ctx| {
         //         load_balancer_ctx.find_interval(interval, minimum, maximum)
         //     })
         //     .unwrap_or_else(|| with_review_fuzz(self.fuzz_factor, interval, minimum, maximum))
-        with_review_fuzz(self.fuzz_factor, interval, minimum, maximum)
+        //
+        // Like Anki, intervals are only load balanced when fuzz is enabled, and
+        // are picked from the same range as fuzz. Easy days are applied by the
+        // load balancer, which knows the day of the week of today. The fuzz
+        // factor follows the fuzz distribution, so that ties between days are
+        // broken the same way as without the load balancer
+        self.load_balancer
+            .zip(self.fuzz_factor)
+            .and_then(|(load_balancer, fuzz_factor)| {
+                let (lower, upper) =
+                    constrained_fuzz_bounds(self.fuzz_profile, interval, minimum, maximum);
+                let fuzz_factor = self.fuzz_profile.distribution.apply(fuzz_factor);
+                load_balancer.find_interval(lower, upper, fuzz_factor, self.easy_days)
+            })
+            .unwrap_or_else(|| {
+                with_review_fuzz(
+                    self.fuzz_profile,
+                    self.fuzz_factor,
+                    interval,
+                    minimum,
+                    maximum,
+                )
+            })
     }
 }
 
 // See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/scheduler/states/fuzz.rs#L65
-fn with_review_fuzz(fuzz_factor: Option<f64>, interval: f64, minimum: u32, maximum: u32) -> u32 {
+fn with_review_fuzz(
+    fuzz_profile: &FuzzProfile,
+    fuzz_factor: Option<f64>,
+    interval: f64,
+    minimum: u32,
+    maximum: u32,
+) -> u32 {
     if let Some(fuzz_factor) = fuzz_factor {
-        let (lower, upper) = constrained_fuzz_bounds(interval, minimum, maximum);
+        let (lower, upper) = constrained_fuzz_bounds(fuzz_profile, interval, minimum, maximum);
+        let fuzz_factor = fuzz_profile.distribution.apply(fuzz_factor);
         (lower as f64 + fuzz_factor * ((1 + upper - lower) as f64)).floor() as u32
     } else {
         (interval.round() as u32).clamp(minimum, maximum)
@@ -279,10 +1194,15 @@ fn with_review_fuzz(fuzz_factor: Option<f64>, interval: f64, minimum: u32, maxim
 /// Return the bounds of the fuzz range, respecting `minimum` and `maximum`.
 /// Ensure the upper bound is larger than the lower bound, if `maximum` allows
 /// it and it is larger than 1.
-fn constrained_fuzz_bounds(interval: f64, minimum: u32, maximum: u32) -> (u32, u32) {
+fn constrained_fuzz_bounds(
+    fuzz_profile: &FuzzProfile,
+    interval: f64,
+    minimum: u32,
+    maximum: u32,
+) -> (u32, u32) {
     let minimum = minimum.min(maximum);
     let interval = interval.clamp(minimum as f64, maximum as f64);
-    let (mut lower, mut upper) = fuzz_bounds(interval);
+    let (mut lower, mut upper) = fuzz_bounds(fuzz_profile, interval);
 
     // minimum <= maximum and lower <= upper are assumed
     // now ensure minimum <= lower <= upper <= maximum
@@ -296,8 +1216,8 @@ fn constrained_fuzz_bounds(interval: f64, minimum: u32, maximum: u32) -> (u32, u
     (lower, upper)
 }
 
-fn fuzz_bounds(interval: f64) -> (u32, u32) {
-    let delta = fuzz_delta(interval);
+fn fuzz_bounds(fuzz_profile: &FuzzProfile, interval: f64) -> (u32, u32) {
+    let delta = fuzz_delta(fuzz_profile, interval);
     (
         (interval - delta).round() as u32,
         (interval + delta).round() as u32,
@@ -308,24 +1228,42 @@ fn fuzz_bounds(interval: f64) -> (u32, u32) {
 /// Short intervals do not get fuzzed. All other intervals get fuzzed by 1 day
 /// plus the number of its days in each defined fuzz range multiplied with the
 /// given factor.
-fn fuzz_delta(interval: f64) -> f64 {
+fn fuzz_delta(fuzz_profile: &FuzzProfile, interval: f64) -> f64 {
     if interval < 2.5 {
         0.0
     } else {
-        FUZZ_RANGES.iter().fold(1.0, |delta, range| {
+        let delta = fuzz_profile.ranges().iter().fold(1.0, |delta, range| {
             delta + range.factor * (interval.min(range.end) - range.start).max(0.0)
-        })
+        });
+        delta * fuzz_profile.strength.max(0.0)
     }
 }
 
 /// Describes a range of days for which a certain amount of fuzz is applied to
 /// the new interval.
-struct FuzzRange {
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FuzzRange {
     start: f64,
+    #[serde(default = "FuzzRange::default_end")]
     end: f64,
     factor: f64,
 }
 
+impl FuzzRange {
+    pub const FIELDS: &'static [&'static str] = &["start", "end", "factor"];
+
+    /// Each day of the interval between `start` and `end` adds `factor` days
+    /// of fuzz. `end` can be `f64::MAX` for a range without an end.
+    pub fn new(start: f64, end: f64, factor: f64) -> Self {
+        Self { start, end, factor }
+    }
+
+    fn default_end() -> f64 {
+        f64::MAX
+    }
+}
+
 static FUZZ_RANGES: [FuzzRange; 3] = [
     FuzzRange {
         start: 2.5,
@@ -353,12 +1291,21 @@ fn get_fuzz_factor(seed: Option<u64>) -> Option<f64> {
 
 #[cfg(test)]
 mod tests {
-    use crate::scheduler::{InternalScheduler, NextState};
+    use crate::card::{Answer, ReviewCard};
+    use crate::fsrs::{Fsrs, MemoryState};
+    use crate::load_balancer::LoadBalancer;
+    use crate::multiplier::constant_multiplier;
+    use crate::scheduler::{
+        FsrsHybrid, FuzzDistribution, FuzzProfile, FuzzRange, GrowthCeiling, InternalScheduler,
+        NextState, Scheduler,
+    };
+    use crate::validation::{deserialized_fields, InvalidField};
     use wasm_bindgen_test::wasm_bindgen_test;
 
     #[derive(Default)]
     struct InternalSchedulerBuilder {
         enable_fuzz: Option<bool>,
+        enable_early_review: Option<bool>,
         maximum_interval: Option<u32>,
         interval_modifier: Option<f64>,
         hard_multiplier: Option<f64>,
@@ -375,6 +1322,11 @@ mod tests {
             self
         }
 
+        fn enable_early_review(mut self, enable_early_review: bool) -> Self {
+            self.enable_early_review = Some(enable_early_review);
+            self
+        }
+
         fn maximum_interval(mut self, maximum_interval: u32) -> Self {
             self.maximum_interval = Some(maximum_interval);
             self
@@ -415,10 +1367,12 @@ mod tests {
             self
         }
 
-        fn build(&self) -> InternalScheduler {
+        fn build<'a>(&self) -> InternalScheduler<'a> {
             InternalScheduler::new(
                 self.enable_fuzz
                     .expect("enable_fuzz should be set in the test"),
+                self.enable_early_review
+                    .expect("enable_early_review should be set in the test"),
                 self.maximum_interval
                     .expect("maximum_interval should be set in the test"),
                 self.interval_modifier
@@ -442,6 +1396,7 @@ mod tests {
     fn default_anki_algorithm_is_used_for_early_reviews() {
         let under_test = InternalSchedulerBuilder::default()
             .enable_fuzz(true)
+            .enable_early_review(false)
             .maximum_interval(36500)
             .interval_modifier(1.0)
             .hard_multiplier(2.0)
@@ -465,9 +1420,10 @@ mod tests {
     }
 
     #[wasm_bindgen_test(unsupported = test)]
-    fn disable_fuzz() {
+    fn early_review_intervals_are_scaled_by_elapsed_days_when_enabled() {
         let under_test = InternalSchedulerBuilder::default()
-            .enable_fuzz(false)
+            .enable_fuzz(true)
+            .enable_early_review(true)
             .maximum_interval(36500)
             .interval_modifier(1.0)
             .hard_multiplier(2.0)
@@ -475,7 +1431,7 @@ mod tests {
             .easy_multiplier(4.0)
             .fuzz_seed(123)
             .scheduled_days(50)
-            .elapsed_days(50)
+            .elapsed_days(25)
             .build();
 
         let result = under_test.next_states();
@@ -483,17 +1439,18 @@ mod tests {
         assert_eq!(
             result,
             NextState {
-                hard_interval: Some(100),
-                good_interval: Some(150),
-                easy_interval: Some(200),
+                hard_interval: Some(50),
+                good_interval: Some(75),
+                easy_interval: Some(87)
             }
         );
     }
 
     #[wasm_bindgen_test(unsupported = test)]
-    fn disable_fuzz_with_overdue_card() {
+    fn early_review_intervals_are_at_least_the_scheduled_interval() {
         let under_test = InternalSchedulerBuilder::default()
             .enable_fuzz(false)
+            .enable_early_review(true)
             .maximum_interval(36500)
             .interval_modifier(1.0)
             .hard_multiplier(2.0)
@@ -501,7 +1458,7 @@ mod tests {
             .easy_multiplier(4.0)
             .fuzz_seed(123)
             .scheduled_days(50)
-            .elapsed_days(100)
+            .elapsed_days(10)
             .build();
 
         let result = under_test.next_states();
@@ -509,25 +1466,107 @@ mod tests {
         assert_eq!(
             result,
             NextState {
-                hard_interval: Some(100),
-                good_interval: Some(225),
-                easy_interval: Some(400),
+                hard_interval: Some(50),
+                good_interval: Some(50),
+                easy_interval: Some(58)
             }
         );
     }
 
     #[wasm_bindgen_test(unsupported = test)]
-    fn enable_fuzz() {
+    fn use_original_anki_algorithm_for_early_reviews_if_multiplier_is_0() {
         let under_test = InternalSchedulerBuilder::default()
-            .enable_fuzz(true)
+            .enable_fuzz(false)
+            .enable_early_review(true)
             .maximum_interval(36500)
             .interval_modifier(1.0)
-            .hard_multiplier(2.0)
-            .good_multiplier(3.0)
+            .hard_multiplier(0.0)
+            .good_multiplier(0.0)
             .easy_multiplier(4.0)
             .fuzz_seed(123)
             .scheduled_days(50)
-            .elapsed_days(50)
+            .elapsed_days(25)
+            .build();
+
+        let result = under_test.next_states();
+
+        assert_eq!(
+            result,
+            NextState {
+                hard_interval: None,
+                good_interval: None,
+                easy_interval: Some(100)
+            }
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn disable_fuzz() {
+        let under_test = InternalSchedulerBuilder::default()
+            .enable_fuzz(false)
+            .enable_early_review(false)
+            .maximum_interval(36500)
+            .interval_modifier(1.0)
+            .hard_multiplier(2.0)
+            .good_multiplier(3.0)
+            .easy_multiplier(4.0)
+            .fuzz_seed(123)
+            .scheduled_days(50)
+            .elapsed_days(50)
+            .build();
+
+        let result = under_test.next_states();
+
+        assert_eq!(
+            result,
+            NextState {
+                hard_interval: Some(100),
+                good_interval: Some(150),
+                easy_interval: Some(200),
+            }
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn disable_fuzz_with_overdue_card() {
+        let under_test = InternalSchedulerBuilder::default()
+            .enable_fuzz(false)
+            .enable_early_review(false)
+            .maximum_interval(36500)
+            .interval_modifier(1.0)
+            .hard_multiplier(2.0)
+            .good_multiplier(3.0)
+            .easy_multiplier(4.0)
+            .fuzz_seed(123)
+            .scheduled_days(50)
+            .elapsed_days(100)
+            .build();
+
+        let result = under_test.next_states();
+
+        assert_eq!(
+            result,
+            NextState {
+                hard_interval: Some(100),
+                good_interval: Some(225),
+                easy_interval: Some(400),
+            }
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn enable_fuzz() {
+        let under_test = InternalSchedulerBuilder::default()
+            .enable_fuzz(true)
+            .enable_early_review(false)
+            .maximum_interval(36500)
+            .interval_modifier(1.0)
+            .hard_multiplier(2.0)
+            .good_multiplier(3.0)
+            .easy_multiplier(4.0)
+            .fuzz_seed(123)
+            .scheduled_days(50)
+            .elapsed_days(50)
             .build();
 
         let result = under_test.next_states();
@@ -542,10 +1581,131 @@ mod tests {
         );
     }
 
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fuzz_profile_without_strength_disables_fuzz() {
+        let fuzz_profile = FuzzProfile::new(None, 0.0, FuzzDistribution::Uniform);
+        let under_test = InternalSchedulerBuilder::default()
+            .enable_fuzz(true)
+            .enable_early_review(false)
+            .maximum_interval(36500)
+            .interval_modifier(1.0)
+            .hard_multiplier(2.0)
+            .good_multiplier(3.0)
+            .easy_multiplier(4.0)
+            .fuzz_seed(123)
+            .scheduled_days(50)
+            .elapsed_days(50)
+            .build()
+            .with_fuzz_profile(Some(&fuzz_profile));
+
+        let result = under_test.next_states();
+
+        assert_eq!(
+            result,
+            NextState {
+                hard_interval: Some(100),
+                good_interval: Some(150),
+                easy_interval: Some(200),
+            }
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fuzz_profile_strength_widens_fuzz_range() {
+        let fuzz_profile = FuzzProfile::new(None, 2.0, FuzzDistribution::Uniform);
+        let under_test = InternalSchedulerBuilder::default()
+            .enable_fuzz(true)
+            .enable_early_review(false)
+            .maximum_interval(36500)
+            .interval_modifier(1.0)
+            .hard_multiplier(2.0)
+            .good_multiplier(3.0)
+            .easy_multiplier(4.0)
+            .fuzz_seed(123)
+            .scheduled_days(50)
+            .elapsed_days(50)
+            .build()
+            .with_fuzz_profile(Some(&fuzz_profile));
+
+        let result = under_test.next_states();
+
+        assert_eq!(
+            result,
+            NextState {
+                hard_interval: Some(91),
+                good_interval: Some(137),
+                easy_interval: Some(184),
+            }
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fuzz_profile_ranges_replace_anki_fuzz_ranges() {
+        let fuzz_profile = FuzzProfile::new(
+            Some(vec![FuzzRange::new(0.0, f64::MAX, 0.2)]),
+            1.0,
+            FuzzDistribution::Uniform,
+        );
+        let under_test = InternalSchedulerBuilder::default()
+            .enable_fuzz(true)
+            .enable_early_review(false)
+            .maximum_interval(36500)
+            .interval_modifier(1.0)
+            .hard_multiplier(2.0)
+            .good_multiplier(3.0)
+            .easy_multiplier(4.0)
+            .fuzz_seed(123)
+            .scheduled_days(50)
+            .elapsed_days(50)
+            .build()
+            .with_fuzz_profile(Some(&fuzz_profile));
+
+        let result = under_test.next_states();
+
+        assert_eq!(
+            result,
+            NextState {
+                hard_interval: Some(86),
+                good_interval: Some(129),
+                easy_interval: Some(173),
+            }
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn triangular_fuzz_distribution_favours_unfuzzed_interval() {
+        let fuzz_profile = FuzzProfile::new(None, 1.0, FuzzDistribution::Triangular);
+        let under_test = InternalSchedulerBuilder::default()
+            .enable_fuzz(true)
+            .enable_early_review(false)
+            .maximum_interval(36500)
+            .interval_modifier(1.0)
+            .hard_multiplier(2.0)
+            .good_multiplier(3.0)
+            .easy_multiplier(4.0)
+            .fuzz_seed(123)
+            .scheduled_days(50)
+            .elapsed_days(50)
+            .build()
+            .with_fuzz_profile(Some(&fuzz_profile));
+
+        let result = under_test.next_states();
+
+        assert_eq!(
+            result,
+            NextState {
+                hard_interval: Some(97),
+                good_interval: Some(146),
+                easy_interval: Some(195),
+            }
+        );
+    }
+
     #[wasm_bindgen_test(unsupported = test)]
     fn intervals_do_not_exceed_maximum_interval() {
         let under_test = InternalSchedulerBuilder::default()
             .enable_fuzz(false)
+            .enable_early_review(false)
             .maximum_interval(125)
             .interval_modifier(1.0)
             .hard_multiplier(2.0)
@@ -572,6 +1732,7 @@ mod tests {
     fn interval_modifier_affects_all_intervals() {
         let under_test = InternalSchedulerBuilder::default()
             .enable_fuzz(false)
+            .enable_early_review(false)
             .maximum_interval(36500)
             .interval_modifier(2.0)
             .hard_multiplier(2.0)
@@ -598,6 +1759,7 @@ mod tests {
     fn use_original_anki_algorithm_if_hard_multiplier_is_0() {
         let under_test = InternalSchedulerBuilder::default()
             .enable_fuzz(false)
+            .enable_early_review(false)
             .maximum_interval(36500)
             .interval_modifier(1.0)
             .hard_multiplier(0.0)
@@ -624,6 +1786,7 @@ mod tests {
     fn use_original_anki_algorithm_if_good_multiplier_is_0() {
         let under_test = InternalSchedulerBuilder::default()
             .enable_fuzz(false)
+            .enable_early_review(false)
             .maximum_interval(36500)
             .interval_modifier(1.0)
             .hard_multiplier(2.0)
@@ -650,6 +1813,7 @@ mod tests {
     fn use_original_anki_algorithm_if_easy_multiplier_is_0() {
         let under_test = InternalSchedulerBuilder::default()
             .enable_fuzz(false)
+            .enable_early_review(false)
             .maximum_interval(36500)
             .interval_modifier(1.0)
             .hard_multiplier(2.0)
@@ -676,6 +1840,7 @@ mod tests {
     fn use_original_anki_algorithm_if_all_multipliers_are_0() {
         let under_test = InternalSchedulerBuilder::default()
             .enable_fuzz(false)
+            .enable_early_review(false)
             .maximum_interval(36500)
             .interval_modifier(1.0)
             .hard_multiplier(0.0)
@@ -697,4 +1862,294 @@ mod tests {
             }
         );
     }
+
+    fn scheduler() -> Scheduler {
+        Scheduler::new(
+            false,
+            36500,
+            2.0,
+            constant_multiplier(1.2),
+            constant_multiplier(2.5),
+            constant_multiplier(3.25),
+        )
+    }
+
+    fn fsrs_hybrid_scheduler() -> Scheduler {
+        scheduler().with_fsrs_hybrid(FsrsHybrid::new(0.9, None))
+    }
+
+    fn review_card(memory_state: Option<MemoryState>) -> ReviewCard {
+        ReviewCard {
+            deck_name: "Japanese".to_string(),
+            scheduled_days: 10,
+            elapsed_days: 10,
+            ease_factor: 2.5,
+            consecutive_successful_reviews: None,
+            consecutive_failed_reviews: None,
+            lapses: 0,
+            leeched: false,
+            memory_state,
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fsrs_hybrid_uses_stability_of_cards_with_memory_state() {
+        let memory_state = MemoryState {
+            stability: 10.0,
+            difficulty: 5.0,
+        };
+        let under_test = fsrs_hybrid_scheduler();
+
+        let result = under_test.next_states(123, &review_card(Some(memory_state)), None, None);
+
+        // The interval at 90% desired retention is the stability, and the
+        // interval modifier is not applied
+        let fsrs = Fsrs::default();
+        let interval = |answer| {
+            fsrs.next_memory_state(memory_state, 10.0, answer)
+                .stability
+                .round() as u32
+        };
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: Some(interval(Answer::Hard)),
+                good_interval: Some(interval(Answer::Good)),
+                easy_interval: Some(interval(Answer::Easy)),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fsrs_hybrid_uses_multipliers_of_cards_without_memory_state() {
+        let under_test = fsrs_hybrid_scheduler();
+
+        let result = under_test.next_states(123, &review_card(None), None, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: Some(24),
+                good_interval: Some(50),
+                easy_interval: Some(65),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn load_balancer_picks_day_with_fewest_cards_due_within_fuzz_range() {
+        let mut due_counts = vec![100; 200];
+        due_counts[145] = 5;
+        due_counts[170] = 0;
+        let load_balancer = LoadBalancer::new(due_counts);
+        let under_test = InternalSchedulerBuilder::default()
+            .enable_fuzz(true)
+            .enable_early_review(false)
+            .maximum_interval(36500)
+            .interval_modifier(1.0)
+            .hard_multiplier(2.0)
+            .good_multiplier(3.0)
+            .easy_multiplier(4.0)
+            .fuzz_seed(123)
+            .scheduled_days(50)
+            .elapsed_days(50)
+            .build()
+            .with_load_balancer(Some(&load_balancer));
+
+        let result = under_test.next_states();
+
+        assert_eq!(result.good_interval, Some(145));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn load_balancer_follows_fuzz_distribution_when_days_are_tied() {
+        let load_balancer = LoadBalancer::new(vec![100; 300]);
+        let fuzz_profile = FuzzProfile::new(None, 1.0, FuzzDistribution::Triangular);
+        let under_test = InternalSchedulerBuilder::default()
+            .enable_fuzz(true)
+            .enable_early_review(false)
+            .maximum_interval(36500)
+            .interval_modifier(1.0)
+            .hard_multiplier(2.0)
+            .good_multiplier(3.0)
+            .easy_multiplier(4.0)
+            .fuzz_seed(123)
+            .scheduled_days(50)
+            .elapsed_days(50)
+            .build()
+            .with_load_balancer(Some(&load_balancer))
+            .with_fuzz_profile(Some(&fuzz_profile));
+
+        let result = under_test.next_states();
+
+        assert_eq!(
+            result,
+            NextState {
+                hard_interval: Some(97),
+                good_interval: Some(146),
+                easy_interval: Some(195),
+            }
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn easy_days_keep_fuzzed_intervals_off_days_without_reviews() {
+        // Today is a Sunday, and only Wednesdays have reviews
+        let load_balancer = LoadBalancer::new(vec![]).with_weekday(0);
+        let easy_days = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+        let under_test = InternalSchedulerBuilder::default()
+            .enable_fuzz(true)
+            .enable_early_review(false)
+            .maximum_interval(36500)
+            .interval_modifier(1.0)
+            .hard_multiplier(2.0)
+            .good_multiplier(3.0)
+            .easy_multiplier(4.0)
+            .fuzz_seed(123)
+            .scheduled_days(50)
+            .elapsed_days(50)
+            .build()
+            .with_load_balancer(Some(&load_balancer))
+            .with_easy_days(Some(&easy_days));
+
+        let result = under_test.next_states();
+
+        assert_eq!(result.good_interval, Some(143));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn every_invalid_fuzz_profile_field_is_reported() {
+        let under_test = FuzzProfile::new(
+            Some(vec![FuzzRange::new(7.0, 2.5, -0.1)]),
+            -1.0,
+            FuzzDistribution::Uniform,
+        );
+
+        let result = under_test.validate();
+
+        assert_eq!(
+            result,
+            vec![
+                InvalidField::new(
+                    "fuzzProfile.ranges",
+                    "end 2.5 is not greater than start 7".to_string()
+                ),
+                InvalidField::new(
+                    "fuzzProfile.ranges",
+                    "factor -0.1 is negative or not a number".to_string()
+                ),
+                InvalidField::new(
+                    "fuzzProfile.strength",
+                    "-1 is negative or not a number".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn button_interval_modifiers_override_interval_modifier() {
+        let under_test = scheduler().with_interval_modifiers(Some(1.0), None, Some(3.0));
+
+        let result = under_test.next_states(123, &review_card(None), None, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: Some(12),
+                good_interval: Some(50),
+                easy_interval: Some(98),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn easy_maximum_interval_can_be_below_good_interval() {
+        let under_test = scheduler().with_maximum_intervals(None, None, Some(30));
+
+        let result = under_test.next_states(123, &review_card(None), None, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: Some(24),
+                good_interval: Some(50),
+                easy_interval: Some(30),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn minimum_gaps_replace_gap_of_one_day() {
+        let under_test = scheduler().with_minimum_gaps(Some(20), None, Some(30));
+
+        let result = under_test.next_states(123, &review_card(None), None, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: Some(30),
+                good_interval: Some(50),
+                easy_interval: Some(80),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn growth_ceiling_limits_growth_ratio() {
+        let under_test = scheduler().with_growth_ceiling(GrowthCeiling::new(Some(4.0), None));
+        let card = ReviewCard {
+            scheduled_days: 3,
+            elapsed_days: 3,
+            ..review_card(None)
+        };
+
+        let result = under_test.next_states(123, &card, None, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: Some(7),
+                good_interval: Some(12),
+                easy_interval: Some(12),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn growth_ceiling_keeps_young_cards_at_mature_threshold() {
+        let under_test = scheduler()
+            .with_maximum_intervals(None, None, Some(36500))
+            .with_growth_ceiling(GrowthCeiling::new(None, Some(21)));
+
+        let result = under_test.next_states(123, &review_card(None), None, None);
+
+        assert_eq!(
+            result,
+            Ok(NextState {
+                hard_interval: Some(21),
+                good_interval: Some(21),
+                easy_interval: Some(21),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn growth_ceiling_does_not_limit_mature_cards() {
+        let under_test = GrowthCeiling::new(None, Some(21));
+
+        assert_eq!(under_test.maximum_interval(20), Some(21));
+        assert_eq!(under_test.maximum_interval(21), None);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn known_fields_match_deserialized_fields() {
+        assert_eq!(deserialized_fields::<FuzzProfile>(), FuzzProfile::FIELDS);
+        assert_eq!(
+            deserialized_fields::<GrowthCeiling>(),
+            GrowthCeiling::FIELDS
+        );
+        assert_eq!(deserialized_fields::<FsrsHybrid>(), FsrsHybrid::FIELDS);
+        assert_eq!(deserialized_fields::<Scheduler>(), Scheduler::FIELDS);
+        assert_eq!(deserialized_fields::<FuzzRange>(), FuzzRange::FIELDS);
+    }
 }
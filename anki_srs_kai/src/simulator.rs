@@ -0,0 +1,547 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::card::{Answer, NextCardState, RelearningCard, ReviewCard};
+use crate::deck_options::DeckOptions;
+use crate::ease_reward::EaseReward;
+use crate::fsrs::Fsrs;
+use crate::multiplier::{Multiplier, MultiplierError, MultiplierFormula};
+use crate::scheduler::Scheduler;
+
+// Anki changes the ease factor by these amounts when pressing Again or Hard,
+// which the custom scheduler leaves to Anki
+//
+// See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/scheduler/states/review.rs
+const EASE_FACTOR_AGAIN_DELTA: f64 = -0.2;
+const EASE_FACTOR_HARD_DELTA: f64 = -0.15;
+const MINIMUM_EASE_FACTOR: f64 = 1.3;
+
+/// How the answer to each review of a simulated card is decided.
+pub enum RecallModel {
+    /// Replay the answers of the review log of a card in order, regardless of
+    /// the day the review is scheduled on. The card is no longer reviewed once
+    /// every answer has been replayed.
+    ReviewLog(Vec<Answer>),
+    /// Recall the card with the probability predicted by the FSRS memory
+    /// model, and press Again otherwise. When the card is recalled, Hard, Good
+    /// or Easy is pressed in proportion to the given weights.
+    Synthetic {
+        hard_weight: f64,
+        good_weight: f64,
+        easy_weight: f64,
+    },
+}
+
+/// A card that has just graduated from learning, which is first reviewed
+/// after `scheduled_days`.
+pub struct SimulatedCard {
+    pub scheduled_days: u32,
+    pub ease_factor: f64,
+    pub recall_model: RecallModel,
+}
+
+/// The native Anki deck options, used by stock SM-2 and for anything that the
+/// custom scheduler leaves to Anki, such as the interval of lapsed cards.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnkiDeckOptions {
+    pub enable_fuzz: bool,
+    pub maximum_interval: u32,
+    pub interval_modifier: f64,
+    pub hard_interval: f64,
+    pub easy_bonus: f64,
+    /// The multiplier applied to the interval of a card after pressing Again,
+    /// which is called "New interval" in the Anki deck options.
+    pub new_interval: f64,
+    pub minimum_interval: u32,
+}
+
+impl Default for AnkiDeckOptions {
+    fn default() -> Self {
+        Self {
+            enable_fuzz: true,
+            maximum_interval: 36500,
+            interval_modifier: 1.0,
+            hard_interval: 1.2,
+            easy_bonus: 1.3,
+            new_interval: 0.0,
+            minimum_interval: 1,
+        }
+    }
+}
+
+impl AnkiDeckOptions {
+    /// Stock SM-2 expressed as deck options of the custom scheduler, where the
+    /// multipliers are the ones Anki uses and the ease reward is disabled.
+    fn stock_sm2(&self) -> DeckOptions {
+        let power_law = |a, c| Multiplier::Formula(MultiplierFormula::PowerLaw { a, b: 0.0, c });
+        DeckOptions::new(
+            EaseReward::new(0, 0.0, 0.0, MINIMUM_EASE_FACTOR, 9.99),
+            Scheduler::new(
+                self.enable_fuzz,
+                self.maximum_interval,
+                self.interval_modifier,
+                power_law(0.0, self.hard_interval),
+                power_law(1.0, 0.0),
+                power_law(self.easy_bonus, 0.0),
+            ),
+        )
+    }
+
+    fn lapse_interval(&self, scheduled_days: u32) -> u32 {
+        ((scheduled_days as f64 * self.new_interval).round() as u32)
+            .max(self.minimum_interval)
+            .clamp(1, self.maximum_interval.max(1))
+    }
+}
+
+/// A single review of a simulated card.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulatedReview {
+    pub day: u32,
+    pub answer: Answer,
+    /// The probability of recalling the card on the day of the review,
+    /// predicted by the FSRS memory model.
+    pub retrievability: f64,
+    /// The interval of the card after the review.
+    pub scheduled_days: u32,
+    /// The ease factor of the card after the review.
+    pub ease_factor: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationReport {
+    /// The reviews of each simulated card, in the same order as the cards, from
+    /// which the interval and ease factor trajectories can be read.
+    pub reviews: Vec<Vec<SimulatedReview>>,
+    /// The number of reviews on each day of the simulation.
+    pub daily_workload: Vec<u32>,
+}
+
+impl SimulationReport {
+    pub fn total_reviews(&self) -> usize {
+        self.reviews.iter().map(Vec::len).sum()
+    }
+
+    /// The average probability of recalling a card when it is reviewed, or
+    /// `None` if no card was reviewed.
+    pub fn predicted_retention(&self) -> Option<f64> {
+        let total_reviews = self.total_reviews();
+        if total_reviews == 0 {
+            return None;
+        }
+        let total_retrievability: f64 = self
+            .reviews
+            .iter()
+            .flatten()
+            .map(|review| review.retrievability)
+            .sum();
+        Some(total_retrievability / total_reviews as f64)
+    }
+}
+
+pub struct Comparison {
+    pub anki_srs_kai: SimulationReport,
+    pub stock_sm2: SimulationReport,
+}
+
+/// Replays cards day by day to evaluate deck options without having to use
+/// them for months. Every card is reviewed on the day it is due, and the
+/// FSRS memory model is used to predict how likely each review is recalled.
+pub struct Simulator {
+    days: u32,
+    seed: u64,
+    fsrs: Fsrs,
+    anki_deck_options: AnkiDeckOptions,
+}
+
+impl Simulator {
+    pub fn new(days: u32, seed: u64, fsrs: Fsrs, anki_deck_options: AnkiDeckOptions) -> Self {
+        Self {
+            days,
+            seed,
+            fsrs,
+            anki_deck_options,
+        }
+    }
+
+    pub fn simulate(
+        &self,
+        deck_options: &DeckOptions,
+        cards: &[SimulatedCard],
+    ) -> Result<SimulationReport, MultiplierError> {
+        self.run(deck_options, &self.anki_deck_options.stock_sm2(), cards)
+    }
+
+    pub fn simulate_stock_sm2(
+        &self,
+        cards: &[SimulatedCard],
+    ) -> Result<SimulationReport, MultiplierError> {
+        let stock_sm2 = self.anki_deck_options.stock_sm2();
+        self.run(&stock_sm2, &stock_sm2, cards)
+    }
+
+    /// Simulate the cards with both the deck options and stock SM-2. The
+    /// synthetic recall model draws the same random numbers in both
+    /// simulations, so any difference comes from the scheduling alone.
+    pub fn compare(
+        &self,
+        deck_options: &DeckOptions,
+        cards: &[SimulatedCard],
+    ) -> Result<Comparison, MultiplierError> {
+        Ok(Comparison {
+            anki_srs_kai: self.simulate(deck_options, cards)?,
+            stock_sm2: self.simulate_stock_sm2(cards)?,
+        })
+    }
+
+    fn run(
+        &self,
+        deck_options: &DeckOptions,
+        stock_sm2: &DeckOptions,
+        cards: &[SimulatedCard],
+    ) -> Result<SimulationReport, MultiplierError> {
+        let mut report = SimulationReport {
+            reviews: Vec::with_capacity(cards.len()),
+            daily_workload: vec![0; self.days as usize],
+        };
+        for (index, card) in cards.iter().enumerate() {
+            let reviews = self.run_card(
+                deck_options,
+                stock_sm2,
+                card,
+                self.seed.wrapping_add(index as u64),
+            )?;
+            for review in &reviews {
+                report.daily_workload[review.day as usize] += 1;
+            }
+            report.reviews.push(reviews);
+        }
+        Ok(report)
+    }
+
+    fn run_card(
+        &self,
+        deck_options: &DeckOptions,
+        stock_sm2: &DeckOptions,
+        card: &SimulatedCard,
+        seed: u64,
+    ) -> Result<Vec<SimulatedReview>, MultiplierError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut answers = match &card.recall_model {
+            RecallModel::ReviewLog(answers) => answers.iter().copied(),
+            RecallModel::Synthetic { .. } => [].iter().copied(),
+        };
+        // Cards are assumed to have graduated from learning by pressing Good
+        let mut memory_state = self.fsrs.initial_memory_state(Answer::Good);
+        let mut review_card = ReviewCard {
+            // Simulated cards do not belong to a deck
+            deck_name: String::new(),
+            scheduled_days: card.scheduled_days,
+            elapsed_days: card.scheduled_days,
+            ease_factor: card.ease_factor,
+            consecutive_successful_reviews: None,
+            consecutive_failed_reviews: None,
+            lapses: 0,
+            // Leeches are not simulated, so only the leech threshold of the
+            // leech policy marks a card as a leech
+            leeched: false,
+            memory_state: None,
+        };
+        let mut day = card.scheduled_days;
+        let mut reviews = Vec::new();
+
+        while day < self.days {
+            let elapsed_days = review_card.elapsed_days as f64;
+            let retrievability = self
+                .fsrs
+                .retrievability(memory_state.stability, elapsed_days);
+            let answer = match card.recall_model {
+                RecallModel::ReviewLog(_) => match answers.next() {
+                    Some(answer) => answer,
+                    None => break,
+                },
+                RecallModel::Synthetic {
+                    hard_weight,
+                    good_weight,
+                    easy_weight,
+                } => {
+                    if rng.random::<f64>() >= retrievability {
+                        Answer::Again
+                    } else {
+                        let weight =
+                            rng.random::<f64>() * (hard_weight + good_weight + easy_weight);
+                        if weight < hard_weight {
+                            Answer::Hard
+                        } else if weight < hard_weight + good_weight {
+                            Answer::Good
+                        } else {
+                            Answer::Easy
+                        }
+                    }
+                }
+            };
+            review_card.memory_state = Some(memory_state);
+            memory_state = self
+                .fsrs
+                .next_memory_state(memory_state, elapsed_days, answer);
+
+            review_card =
+                self.answer_card(deck_options, stock_sm2, rng.random(), &review_card, answer)?;
+            reviews.push(SimulatedReview {
+                day,
+                answer,
+                retrievability,
+                scheduled_days: review_card.scheduled_days,
+                ease_factor: review_card.ease_factor,
+            });
+            day += review_card.scheduled_days;
+        }
+
+        Ok(reviews)
+    }
+
+    /// Return the card after answering it, using stock SM-2 for anything that
+    /// the custom scheduler leaves to Anki.
+    fn answer_card(
+        &self,
+        deck_options: &DeckOptions,
+        stock_sm2: &DeckOptions,
+        fuzz_seed: u64,
+        card: &ReviewCard,
+        answer: Answer,
+    ) -> Result<ReviewCard, MultiplierError> {
+        // Cards are simulated one at a time, so the number of cards due on
+        // future days is not known to load balance the intervals
+        let next_card_states = deck_options.next_card_states(fuzz_seed, card, None)?;
+        let next_card_state = match answer {
+            Answer::Again => next_card_states.again,
+            Answer::Hard => next_card_states.hard,
+            Answer::Good => next_card_states.good,
+            Answer::Easy => next_card_states.easy,
+        };
+        let NextCardState {
+            scheduled_days,
+            ease_factor,
+            consecutive_successful_reviews,
+            consecutive_failed_reviews,
+        } = next_card_state;
+
+        let ease_factor = ease_factor.unwrap_or_else(|| match answer {
+            Answer::Again => (card.ease_factor + EASE_FACTOR_AGAIN_DELTA).max(MINIMUM_EASE_FACTOR),
+            Answer::Hard => (card.ease_factor + EASE_FACTOR_HARD_DELTA).max(MINIMUM_EASE_FACTOR),
+            Answer::Good | Answer::Easy => card.ease_factor,
+        });
+
+        let lapses = if answer == Answer::Again {
+            card.lapses + 1
+        } else {
+            card.lapses
+        };
+        let scheduled_days = match (answer, scheduled_days) {
+            (_, Some(scheduled_days)) => scheduled_days,
+            // Relearning is assumed to graduate by pressing Good
+            (Answer::Again, None) => deck_options
+                .next_relearning_states(
+                    fuzz_seed,
+                    &RelearningCard {
+                        deck_name: card.deck_name.clone(),
+                        ease_factor,
+                        previous_interval: card.scheduled_days,
+                        lapses,
+                        leeched: card.leeched,
+                        memory_state: card.memory_state,
+                    },
+                    None,
+                )?
+                .and_then(|next_states| next_states.good_interval)
+                .unwrap_or_else(|| self.anki_deck_options.lapse_interval(card.scheduled_days)),
+            (_, None) => {
+                let stock_sm2_states = stock_sm2.next_card_states(fuzz_seed, card, None)?;
+                match answer {
+                    Answer::Hard => stock_sm2_states.hard,
+                    Answer::Easy => stock_sm2_states.easy,
+                    Answer::Again | Answer::Good => stock_sm2_states.good,
+                }
+                .scheduled_days
+                .unwrap_or(card.scheduled_days)
+            }
+        };
+        Ok(ReviewCard {
+            deck_name: card.deck_name.clone(),
+            scheduled_days,
+            elapsed_days: scheduled_days,
+            ease_factor,
+            consecutive_successful_reviews,
+            consecutive_failed_reviews,
+            lapses,
+            leeched: false,
+            memory_state: card.memory_state,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::card::Answer;
+    use crate::deck_options::DeckOptions;
+    use crate::ease_reward::EaseReward;
+    use crate::fsrs::Fsrs;
+    use crate::lapse::Lapse;
+    use crate::multiplier::constant_multiplier;
+    use crate::scheduler::Scheduler;
+    use crate::simulator::{AnkiDeckOptions, RecallModel, SimulatedCard, Simulator};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn simulator(days: u32) -> Simulator {
+        Simulator::new(
+            days,
+            123,
+            Fsrs::default(),
+            AnkiDeckOptions {
+                enable_fuzz: false,
+                ..AnkiDeckOptions::default()
+            },
+        )
+    }
+
+    fn deck_options() -> DeckOptions {
+        DeckOptions::new(
+            EaseReward::new(2, 0.05, 0.05, 1.30, 2.50),
+            Scheduler::new(
+                false,
+                36500,
+                1.0,
+                constant_multiplier(1.5),
+                constant_multiplier(2.0),
+                constant_multiplier(3.0),
+            ),
+        )
+    }
+
+    fn review_log_card(answers: Vec<Answer>) -> SimulatedCard {
+        SimulatedCard {
+            scheduled_days: 1,
+            ease_factor: 2.5,
+            recall_model: RecallModel::ReviewLog(answers),
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn review_log_is_replayed_with_deck_options() {
+        let under_test = simulator(100);
+
+        let result = under_test
+            .simulate(
+                &deck_options(),
+                &[review_log_card(vec![
+                    Answer::Good,
+                    Answer::Good,
+                    Answer::Again,
+                    Answer::Good,
+                ])],
+            )
+            .expect("simulation should succeed");
+
+        let trajectory = result.reviews[0]
+            .iter()
+            .map(|review| (review.day, review.scheduled_days, review.ease_factor))
+            .collect::<Vec<(u32, u32, f64)>>();
+        assert_eq!(
+            trajectory,
+            vec![(1, 3, 2.5), (4, 6, 2.5), (10, 1, 2.3), (11, 3, 2.3)]
+        );
+        assert_eq!(result.total_reviews(), 4);
+        assert_eq!(result.daily_workload[4], 1);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn lapse_multiplier_is_used_after_again() {
+        let under_test = simulator(100);
+
+        let result = under_test
+            .simulate(
+                &deck_options().with_lapse(Lapse::new(constant_multiplier(0.5))),
+                &[review_log_card(vec![
+                    Answer::Good,
+                    Answer::Good,
+                    Answer::Again,
+                    Answer::Good,
+                ])],
+            )
+            .expect("simulation should succeed");
+
+        let trajectory = result.reviews[0]
+            .iter()
+            .map(|review| (review.day, review.scheduled_days, review.ease_factor))
+            .collect::<Vec<(u32, u32, f64)>>();
+        assert_eq!(
+            trajectory,
+            vec![(1, 3, 2.5), (4, 6, 2.5), (10, 3, 2.3), (13, 6, 2.3)]
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn review_log_is_replayed_with_stock_sm2() {
+        let under_test = simulator(100);
+
+        let result = under_test
+            .simulate_stock_sm2(&[review_log_card(vec![
+                Answer::Good,
+                Answer::Hard,
+                Answer::Easy,
+            ])])
+            .expect("simulation should succeed");
+
+        let trajectory = result.reviews[0]
+            .iter()
+            .map(|review| (review.day, review.scheduled_days, review.ease_factor))
+            .collect::<Vec<(u32, u32, f64)>>();
+        assert_eq!(trajectory, vec![(1, 3, 2.5), (4, 4, 2.35), (8, 12, 2.5)]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn simulation_stops_at_the_last_day() {
+        let under_test = simulator(5);
+
+        let result = under_test
+            .simulate_stock_sm2(&[review_log_card(vec![Answer::Good; 10])])
+            .expect("simulation should succeed");
+
+        assert_eq!(result.total_reviews(), 2);
+        assert_eq!(result.daily_workload, vec![0, 1, 0, 0, 1]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn longer_intervals_predict_lower_retention() {
+        let under_test = simulator(3650);
+        let cards = (0..20)
+            .map(|_| SimulatedCard {
+                scheduled_days: 1,
+                ease_factor: 2.5,
+                recall_model: RecallModel::Synthetic {
+                    hard_weight: 0.15,
+                    good_weight: 0.75,
+                    easy_weight: 0.1,
+                },
+            })
+            .collect::<Vec<SimulatedCard>>();
+        let deck_options = DeckOptions::new(
+            EaseReward::new(0, 0.0, 0.0, 1.30, 2.50),
+            Scheduler::new(
+                false,
+                36500,
+                1.0,
+                constant_multiplier(3.0),
+                constant_multiplier(6.0),
+                constant_multiplier(8.0),
+            ),
+        );
+
+        let result = under_test
+            .compare(&deck_options, &cards)
+            .expect("simulation should succeed");
+
+        assert!(result.anki_srs_kai.total_reviews() < result.stock_sm2.total_reviews());
+        assert!(result.anki_srs_kai.predicted_retention() < result.stock_sm2.predicted_retention());
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt::{Display, Formatter};
+
+use serde::Deserialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::wasm_bindgen;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{JsCast, JsValue};
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console, js_name = warn)]
+    fn console_warn(message: &str);
+}
+
+/// Whether invalid deck options should stop the custom scheduler, or only be
+/// logged to the console while the remaining valid deck options are used.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValidationMode {
+    #[default]
+    Strict,
+    Warn,
+}
+
+/// A field of a deck options section that has an invalid value.
+#[derive(Debug, PartialEq)]
+pub struct InvalidField {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl InvalidField {
+    pub fn new(field: &'static str, message: String) -> Self {
+        Self { field, message }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ValidationError {
+    deck_name: String,
+    path: String,
+    message: String,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "deck \"{}\": {}", self.deck_name, self.message)
+        } else {
+            write!(
+                f,
+                "deck \"{}\": {} {}",
+                self.deck_name, self.path, self.message
+            )
+        }
+    }
+}
+
+/// Collects every problem found in the deck options so that they can all be
+/// reported at once, instead of stopping at the first one.
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<ValidationError>,
+}
+
+impl Validator {
+    pub fn add_error(&mut self, deck_name: &str, path: &str, message: String) {
+        self.errors.push(ValidationError {
+            deck_name: deck_name.to_string(),
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    pub fn add_invalid_fields(
+        &mut self,
+        deck_name: &str,
+        section: &str,
+        invalid_fields: Vec<InvalidField>,
+    ) {
+        for invalid_field in invalid_fields {
+            self.add_error(
+                deck_name,
+                &format!("{section}.{}", invalid_field.field),
+                invalid_field.message,
+            );
+        }
+    }
+
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.errors
+    }
+
+    /// Report any of the keys of a section that are not one of the known
+    /// fields. Typos in optional fields would otherwise be silently ignored.
+    pub fn add_unknown_keys(
+        &mut self,
+        deck_name: &str,
+        section: &str,
+        keys: impl IntoIterator<Item = String>,
+        known_fields: &[&str],
+    ) {
+        for key in keys {
+            if !known_fields.contains(&key.as_str()) {
+                let path = if section.is_empty() {
+                    key
+                } else {
+                    format!("{section}.{key}")
+                };
+                self.add_error(deck_name, &path, "is not a known field".to_string());
+            }
+        }
+    }
+
+    /// Report any keys of the JavaScript object that are not one of the known
+    /// fields.
+    #[cfg(feature = "wasm")]
+    pub fn check_unknown_keys(
+        &mut self,
+        deck_name: &str,
+        section: &str,
+        value: &JsValue,
+        known_fields: &[&str],
+    ) {
+        let Some(object) = value.dyn_ref::<js_sys::Object>() else {
+            return;
+        };
+        let keys = js_sys::Object::keys(object)
+            .into_iter()
+            .filter_map(|key| key.as_string());
+        self.add_unknown_keys(deck_name, section, keys, known_fields);
+    }
+
+    /// Report any keys of the JavaScript object nested in the field of a
+    /// section that are not one of the known fields, such as
+    /// `easeReward.easeGravity`.
+    #[cfg(feature = "wasm")]
+    pub fn check_nested_unknown_keys(
+        &mut self,
+        deck_name: &str,
+        section: &str,
+        value: &JsValue,
+        field: &str,
+        known_fields: &[&str],
+    ) {
+        if let Ok(nested_value) = js_sys::Reflect::get(value, &JsValue::from_str(field)) {
+            self.check_unknown_keys(
+                deck_name,
+                &format!("{section}.{field}"),
+                &nested_value,
+                known_fields,
+            );
+        }
+    }
+
+    /// Return every collected problem as a single error in strict mode. In
+    /// warn mode, every problem is logged to the console instead.
+    #[cfg(feature = "wasm")]
+    pub fn finish(self, validation_mode: ValidationMode) -> Result<(), JsValue> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        match validation_mode {
+            ValidationMode::Strict => Err(js_sys::Error::new(
+                &self
+                    .errors
+                    .iter()
+                    .map(ValidationError::to_string)
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            )
+            .into()),
+            ValidationMode::Warn => {
+                for error in &self.errors {
+                    console_warn(&error.to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Return the fields that the `Deserialize` implementation of a struct reads,
+/// so that the lists of known fields used to report unknown keys can be checked
+/// against them.
+#[cfg(test)]
+pub(crate) fn deserialized_fields<T: serde::de::DeserializeOwned>() -> &'static [&'static str] {
+    use serde::de::value::Error;
+    use serde::de::{Error as _, Visitor};
+    use serde::forward_to_deserialize_any;
+
+    struct FieldsRecorder<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> serde::Deserializer<'de> for FieldsRecorder<'_> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+            Err(Error::custom("expected a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Error> {
+            *self.0 = fields;
+            Err(Error::custom("the fields are recorded"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldsRecorder(&mut fields));
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::validation::{InvalidField, Validator};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn invalid_fields_are_reported_with_deck_name_and_field_path() {
+        let mut under_test = Validator::default();
+
+        under_test.add_invalid_fields(
+            "Vocab",
+            "easeReward",
+            vec![
+                InvalidField::new("maximumEase", "12 exceeds 9.99".to_string()),
+                InvalidField::new("minimumEase", "1 is below 1.3".to_string()),
+            ],
+        );
+
+        let result = under_test
+            .errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            result,
+            vec![
+                "deck \"Vocab\": easeReward.maximumEase 12 exceeds 9.99",
+                "deck \"Vocab\": easeReward.minimumEase 1 is below 1.3",
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn errors_without_a_field_path_are_reported_with_deck_name() {
+        let mut under_test = Validator::default();
+
+        under_test.add_error("Vocab", "", "missing field `scheduler`".to_string());
+
+        let result = under_test
+            .errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>();
+
+        assert_eq!(result, vec!["deck \"Vocab\": missing field `scheduler`"]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn unknown_keys_are_reported_with_nested_field_path() {
+        let mut under_test = Validator::default();
+
+        under_test.add_unknown_keys(
+            "Vocab",
+            "easeReward.easeGravity",
+            ["targetEase".to_string(), "strenght".to_string()],
+            &["targetEase", "strength"],
+        );
+
+        let result = under_test
+            .errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            result,
+            vec!["deck \"Vocab\": easeReward.easeGravity.strenght is not a known field"]
+        );
+    }
+}
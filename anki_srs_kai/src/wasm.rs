@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::anki;
+use crate::anki::javascript::{CONTEXT, CUSTOM_DATA, STATES};
+use crate::anki::{
+    FilteredState, NormalState, SchedulingState, SchedulingStateKind, SchedulingStates,
+};
+use crate::card::{LearningCard, NextCardState, RelearningCard, ReviewCard};
+use crate::deck_options::{DeckNameToDeckOptions, DeckOptions};
+use crate::ease_penalty::EasePenalty;
+use crate::ease_reward::{EaseGravity, EaseReward, StreakCredit};
+use crate::fsrs::MemoryState;
+use crate::lapse::Lapse;
+use crate::learning::Learning;
+use crate::leech_policy::LeechPolicy;
+use crate::load_balancer::LoadBalancer;
+use crate::multiplier::MultiplierFormula;
+use crate::scheduler::{FsrsHybrid, FuzzProfile, FuzzRange, GrowthCeiling, Scheduler};
+use crate::validation::{ValidationMode, Validator};
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SchedulingOptions {
+    validation: ValidationMode,
+    /// The number of cards due on each day, starting from today, to load
+    /// balance the intervals with.
+    due_counts: Option<Vec<u32>>,
+    /// The day of the week of today, from 0 for Sunday to 6 for Saturday, to
+    /// apply easy days with. Defaults to the day of the week of Anki's day.
+    weekday: Option<u32>,
+    /// The hour at which Anki starts the next day, which is the "Next day
+    /// starts at" setting of Anki. Defaults to Anki's default of 4am.
+    rollover_hour: Option<u32>,
+}
+
+/// Anki starts the next day at 4am unless configured otherwise.
+const DEFAULT_ROLLOVER_HOUR: u32 = 4;
+const MILLISECONDS_PER_HOUR: f64 = 60.0 * 60.0 * 1000.0;
+
+/// Return the day of the week of today in Anki, where reviews past midnight but
+/// before the rollover hour still count towards the previous day.
+fn anki_weekday(rollover_hour: u32) -> u32 {
+    let now = js_sys::Date::new_0();
+    let start_of_anki_day = js_sys::Date::new(&JsValue::from_f64(
+        now.get_time() - f64::from(rollover_hour.min(23)) * MILLISECONDS_PER_HOUR,
+    ));
+    start_of_anki_day.get_day()
+}
+
+/// Return the field of a JavaScript object, or `undefined` if the value is not
+/// an object.
+fn get_field(value: &JsValue, field: &str) -> JsValue {
+    js_sys::Reflect::get(value, &JsValue::from_str(field)).unwrap_or(JsValue::UNDEFINED)
+}
+
+/// Report any unknown keys of a multiplier formula, whose known fields depend
+/// on its type. Multiplier functions and unknown types are left to
+/// deserialization.
+fn check_multiplier_unknown_keys(
+    validator: &mut Validator,
+    deck_name: &str,
+    section: &str,
+    value: &JsValue,
+    field: &str,
+) {
+    let formula = get_field(value, field);
+    if formula.is_function() {
+        return;
+    }
+    let Some(fields) = get_field(&formula, "type")
+        .as_string()
+        .and_then(|formula_type| MultiplierFormula::fields(&formula_type))
+    else {
+        return;
+    };
+    validator.check_unknown_keys(deck_name, &format!("{section}.{field}"), &formula, fields);
+}
+
+/// Deserialize and validate the options of every deck, collecting every
+/// problem found instead of stopping at the first one. Decks with options that
+/// cannot be deserialized, or with a deck name that is an invalid regular
+/// expression, are left out.
+fn parse_deck_name_to_deck_options(
+    deck_name_to_deck_options: JsValue,
+    validation_mode: ValidationMode,
+) -> Result<DeckNameToDeckOptions<DeckOptions>, JsValue> {
+    let Some(object) = deck_name_to_deck_options.dyn_ref::<js_sys::Object>() else {
+        let deck_name_to_deck_options: HashMap<String, DeckOptions> =
+            serde_wasm_bindgen::from_value(deck_name_to_deck_options)?;
+        return DeckNameToDeckOptions::try_from(deck_name_to_deck_options)
+            .map_err(|e| js_sys::Error::new(&e.to_string()).into());
+    };
+
+    let mut validator = Validator::default();
+    let mut parsed_deck_name_to_deck_options = DeckNameToDeckOptions::default();
+    for entry in js_sys::Object::entries(object) {
+        let entry = js_sys::Array::from(&entry);
+        let Some(deck_name) = entry.get(0).as_string() else {
+            continue;
+        };
+        let deck_options = entry.get(1);
+
+        validator.check_unknown_keys(&deck_name, "", &deck_options, DeckOptions::FIELDS);
+        for (section, fields) in [
+            ("easeReward", EaseReward::FIELDS),
+            ("easePenalty", EasePenalty::FIELDS),
+            ("scheduler", Scheduler::FIELDS),
+            ("lapse", Lapse::FIELDS),
+            ("learning", Learning::FIELDS),
+            ("leechPolicy", LeechPolicy::FIELDS),
+        ] {
+            if let Ok(value) = js_sys::Reflect::get(&deck_options, &JsValue::from_str(section)) {
+                validator.check_unknown_keys(&deck_name, section, &value, fields);
+            }
+        }
+        for (section, field, fields) in [
+            ("easeReward", "easeGravity", EaseGravity::FIELDS),
+            ("easeReward", "streakCredit", StreakCredit::FIELDS),
+            ("scheduler", "fsrsHybrid", FsrsHybrid::FIELDS),
+            ("scheduler", "fuzzProfile", FuzzProfile::FIELDS),
+            ("scheduler", "growthCeiling", GrowthCeiling::FIELDS),
+        ] {
+            validator.check_nested_unknown_keys(
+                &deck_name,
+                section,
+                &get_field(&deck_options, section),
+                field,
+                fields,
+            );
+        }
+        let fuzz_ranges = get_field(
+            &get_field(&get_field(&deck_options, "scheduler"), "fuzzProfile"),
+            "ranges",
+        );
+        if let Some(fuzz_ranges) = fuzz_ranges.dyn_ref::<js_sys::Array>() {
+            for fuzz_range in fuzz_ranges.iter() {
+                validator.check_unknown_keys(
+                    &deck_name,
+                    "scheduler.fuzzProfile.ranges",
+                    &fuzz_range,
+                    FuzzRange::FIELDS,
+                );
+            }
+        }
+        for (section, field) in [
+            ("scheduler", "calculateHardMultiplier"),
+            ("scheduler", "calculateGoodMultiplier"),
+            ("scheduler", "calculateEasyMultiplier"),
+            ("lapse", "calculateLapseMultiplier"),
+        ] {
+            check_multiplier_unknown_keys(
+                &mut validator,
+                &deck_name,
+                section,
+                &get_field(&deck_options, section),
+                field,
+            );
+        }
+
+        match serde_wasm_bindgen::from_value::<DeckOptions>(deck_options) {
+            Err(e) => validator.add_error(&deck_name, "", e.to_string()),
+            Ok(deck_options) => {
+                deck_options.validate(&deck_name, &mut validator);
+                if let Err(e) =
+                    parsed_deck_name_to_deck_options.insert(deck_name.clone(), deck_options)
+                {
+                    validator.add_error(&deck_name, "", e.to_string());
+                }
+            }
+        }
+    }
+    validator.finish(validation_mode)?;
+
+    Ok(parsed_deck_name_to_deck_options)
+}
+
+fn get_context() -> anki::javascript::SchedulingContext {
+    CONTEXT.with(anki::javascript::SchedulingContext::clone)
+}
+
+fn get_states() -> anki::javascript::SchedulingStates {
+    STATES.with(anki::javascript::SchedulingStates::clone)
+}
+
+fn get_custom_data() -> anki::javascript::CustomDataStates {
+    CUSTOM_DATA.with(anki::javascript::CustomDataStates::clone)
+}
+
+fn set_review_state(review: anki::javascript::ReviewState, next_card_state: &NextCardState) {
+    if let Some(ease_factor) = next_card_state.ease_factor {
+        review.set_ease_factor(ease_factor);
+    }
+    if let Some(scheduled_days) = next_card_state.scheduled_days {
+        review.set_scheduled_days(scheduled_days);
+    }
+}
+
+/// Return the JavaScript review state of the answer button, if the card will be
+/// in the review state after pressing it.
+fn review_state(
+    state: &SchedulingState,
+    javascript_state: anki::javascript::SchedulingState,
+) -> Option<anki::javascript::ReviewState> {
+    match &state.kind {
+        SchedulingStateKind::Normal(NormalState::Review(_)) => {
+            Some(javascript_state.normal().review())
+        }
+        SchedulingStateKind::Filtered(FilteredState::Rescheduling(rescheduling)) => {
+            match rescheduling.original_state {
+                NormalState::Review(_) => Some(
+                    javascript_state
+                        .filtered()
+                        .rescheduling()
+                        .original_state()
+                        .review(),
+                ),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Return the JavaScript review state of the relearning card, if the card will
+/// be in the relearning state after pressing the answer button.
+fn relearning_review_state(
+    state: &SchedulingState,
+    javascript_state: anki::javascript::SchedulingState,
+) -> Option<anki::javascript::ReviewState> {
+    match &state.kind {
+        SchedulingStateKind::Normal(NormalState::Relearning(_)) => {
+            Some(javascript_state.normal().relearning().review())
+        }
+        SchedulingStateKind::Filtered(FilteredState::Rescheduling(rescheduling)) => {
+            match rescheduling.original_state {
+                NormalState::Relearning(_) => Some(
+                    javascript_state
+                        .filtered()
+                        .rescheduling()
+                        .original_state()
+                        .relearning()
+                        .review(),
+                ),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Set the intervals of a relearning card graduating back to review by pressing
+/// Good or Easy, if the lapse section is configured.
+fn set_relearning_graduation_states(
+    deck_name: &str,
+    deck_options: &DeckOptions,
+    states: &SchedulingStates,
+    review: &anki::ReviewState,
+    load_balancer: Option<&LoadBalancer>,
+) -> Result<(), JsValue> {
+    let card = RelearningCard {
+        deck_name: deck_name.to_string(),
+        ease_factor: review.ease_factor,
+        // Cards that lapsed before the lapse section was configured use the
+        // interval that Anki set when the card lapsed
+        previous_interval: get_custom_data()
+            .good()
+            .p()
+            .unwrap_or(review.scheduled_days),
+        lapses: review.lapses,
+        leeched: review.leeched,
+        memory_state: review
+            .memory_state
+            .as_ref()
+            .map(|memory_state| MemoryState {
+                stability: memory_state.stability,
+                difficulty: memory_state.difficulty,
+            }),
+    };
+    let Some(next_states) = deck_options
+        .next_relearning_states(get_context().seed(), &card, load_balancer)
+        .map_err(|e| js_sys::Error::new(&format!("deck \"{deck_name}\": lapse.{e}")))?
+    else {
+        return Ok(());
+    };
+
+    for (state, javascript_state, custom_data, interval) in [
+        (
+            &states.good,
+            get_states().good(),
+            get_custom_data().good(),
+            next_states.good_interval,
+        ),
+        (
+            &states.easy,
+            get_states().easy(),
+            get_custom_data().easy(),
+            next_states.easy_interval,
+        ),
+    ] {
+        if let Some(review) = review_state(state, javascript_state) {
+            if let Some(interval) = interval {
+                review.set_scheduled_days(interval);
+            }
+            custom_data.set_p(None);
+        }
+    }
+    Ok(())
+}
+
+/// Return the number of learning steps of a new card, which is the number of
+/// remaining steps after pressing Again, or 0 if there are no learning steps.
+fn new_card_learning_steps(states: &SchedulingStates) -> u32 {
+    let again = match &states.again.kind {
+        SchedulingStateKind::Normal(normal) => normal,
+        SchedulingStateKind::Filtered(FilteredState::Rescheduling(rescheduling)) => {
+            &rescheduling.original_state
+        }
+        SchedulingStateKind::Filtered(FilteredState::Preview(_)) => return 0,
+    };
+    match again {
+        NormalState::Learning(learning) => learning.remaining_steps,
+        _ => 0,
+    }
+}
+
+/// Set the intervals of a new or learning card graduating to review by pressing
+/// Good on the last learning step or Easy, if the learning section is
+/// configured.
+fn set_learning_graduation_states(
+    deck_name: &str,
+    deck_options: &DeckOptions,
+    states: &SchedulingStates,
+    card: &LearningCard,
+    load_balancer: Option<&LoadBalancer>,
+) -> Result<(), JsValue> {
+    let Some(next_states) = deck_options
+        .next_learning_states(get_context().seed(), card, load_balancer)
+        .map_err(|e| js_sys::Error::new(&format!("deck \"{deck_name}\": learning.{e}")))?
+    else {
+        return Ok(());
+    };
+
+    // Pressing Good before the last learning step keeps the card in learning,
+    // in which case there is no review state to set
+    for (state, javascript_state, interval) in [
+        (&states.good, get_states().good(), next_states.good_interval),
+        (&states.easy, get_states().easy(), next_states.easy_interval),
+    ] {
+        if let (Some(review), Some(interval)) = (review_state(state, javascript_state), interval) {
+            review.set_scheduled_days(interval);
+        }
+    }
+    Ok(())
+}
+
+#[wasm_bindgen(js_name = calculateNextCardStates)]
+pub fn calculate_next_card_states(
+    deck_name_to_deck_options: JsValue,
+    states: JsValue,
+    scheduling_options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let scheduling_options: SchedulingOptions =
+        serde_wasm_bindgen::from_value::<Option<SchedulingOptions>>(scheduling_options)?
+            .unwrap_or_default();
+    let deck_name_to_deck_options =
+        parse_deck_name_to_deck_options(deck_name_to_deck_options, scheduling_options.validation)?;
+    let weekday = scheduling_options.weekday.unwrap_or_else(|| {
+        anki_weekday(
+            scheduling_options
+                .rollover_hour
+                .unwrap_or(DEFAULT_ROLLOVER_HOUR),
+        )
+    });
+    let load_balancer =
+        LoadBalancer::new(scheduling_options.due_counts.unwrap_or_default()).with_weekday(weekday);
+    let deck_name = get_context().deck_name();
+    let deck_options = match deck_name_to_deck_options.find(&deck_name) {
+        None => return Ok(JsValue::NULL),
+        Some(deck_options) => deck_options,
+    };
+
+    let states: SchedulingStates = serde_wasm_bindgen::from_value(states)?;
+
+    let current_state = match &states.current.kind {
+        SchedulingStateKind::Normal(normal) => normal,
+        SchedulingStateKind::Filtered(filtered) => match filtered {
+            FilteredState::Preview(_) => return Ok(JsValue::NULL),
+            FilteredState::Rescheduling(rescheduling) => &rescheduling.original_state,
+        },
+    };
+    let current_review = match current_state {
+        // New and learning cards keep Anki's "Graduating interval" and "Easy
+        // interval" settings behaviour unless the learning section is
+        // configured. A new card has not been answered yet, so all of its
+        // learning steps remain and no time has elapsed.
+        NormalState::New(_) => {
+            let card = LearningCard {
+                remaining_steps: new_card_learning_steps(&states),
+                elapsed_secs: 0,
+            };
+            set_learning_graduation_states(
+                &deck_name,
+                deck_options,
+                &states,
+                &card,
+                Some(&load_balancer),
+            )?;
+            return Ok(JsValue::NULL);
+        }
+        NormalState::Learning(learning) => {
+            let card = LearningCard {
+                remaining_steps: learning.remaining_steps,
+                elapsed_secs: learning.elapsed_secs,
+            };
+            set_learning_graduation_states(
+                &deck_name,
+                deck_options,
+                &states,
+                &card,
+                Some(&load_balancer),
+            )?;
+            return Ok(JsValue::NULL);
+        }
+        // Relearning cards keep Anki's "New Interval" setting behaviour unless
+        // the lapse section is configured
+        NormalState::Relearning(relearning) => {
+            set_relearning_graduation_states(
+                &deck_name,
+                deck_options,
+                &states,
+                &relearning.review,
+                Some(&load_balancer),
+            )?;
+            return Ok(JsValue::NULL);
+        }
+        NormalState::Review(review) => review,
+    };
+
+    let card = ReviewCard {
+        deck_name: deck_name.clone(),
+        scheduled_days: current_review.scheduled_days,
+        elapsed_days: current_review.elapsed_days,
+        ease_factor: current_review.ease_factor,
+        consecutive_successful_reviews: get_custom_data().good().c(),
+        consecutive_failed_reviews: get_custom_data().good().f(),
+        lapses: current_review.lapses,
+        leeched: current_review.leeched,
+        memory_state: current_review
+            .memory_state
+            .as_ref()
+            .map(|memory_state| MemoryState {
+                stability: memory_state.stability,
+                difficulty: memory_state.difficulty,
+            }),
+    };
+    let next_card_states = deck_options
+        .next_card_states(get_context().seed(), &card, Some(&load_balancer))
+        .map_err(|e| js_sys::Error::new(&format!("deck \"{deck_name}\": scheduler.{e}")))?;
+
+    if let Some(review) = relearning_review_state(&states.again, get_states().again()) {
+        set_review_state(review, &next_card_states.again);
+        if deck_options.lapse().is_some() {
+            // Keep the interval before the lapse for when the card graduates
+            // back to review
+            get_custom_data()
+                .again()
+                .set_p(Some(current_review.scheduled_days));
+        }
+        get_custom_data()
+            .again()
+            .set_c(next_card_states.again.consecutive_successful_reviews);
+        get_custom_data()
+            .again()
+            .set_f(next_card_states.again.consecutive_failed_reviews);
+    } else if let Some(review) = review_state(&states.again, get_states().again()) {
+        // Without relearning steps, pressing Again keeps the card in review
+        set_review_state(review, &next_card_states.again);
+        get_custom_data()
+            .again()
+            .set_c(next_card_states.again.consecutive_successful_reviews);
+        get_custom_data()
+            .again()
+            .set_f(next_card_states.again.consecutive_failed_reviews);
+    }
+
+    if let Some(review) = review_state(&states.hard, get_states().hard()) {
+        set_review_state(review, &next_card_states.hard);
+        get_custom_data()
+            .hard()
+            .set_c(next_card_states.hard.consecutive_successful_reviews);
+        get_custom_data()
+            .hard()
+            .set_f(next_card_states.hard.consecutive_failed_reviews);
+    }
+
+    if let Some(review) = review_state(&states.good, get_states().good()) {
+        set_review_state(review, &next_card_states.good);
+        get_custom_data()
+            .good()
+            .set_c(next_card_states.good.consecutive_successful_reviews);
+        get_custom_data()
+            .good()
+            .set_f(next_card_states.good.consecutive_failed_reviews);
+    }
+
+    if let Some(review) = review_state(&states.easy, get_states().easy()) {
+        set_review_state(review, &next_card_states.easy);
+        get_custom_data()
+            .easy()
+            .set_c(next_card_states.easy.consecutive_successful_reviews);
+        get_custom_data()
+            .easy()
+            .set_f(next_card_states.easy.consecutive_failed_reviews);
+    }
+
+    Ok(JsValue::NULL)
+}
@@ -0,0 +1,100 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// Command line arguments of the form `--name value` and `--flag`.
+pub struct Arguments {
+    values: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl Arguments {
+    pub fn parse(arguments: &[String], options: &[&str], flags: &[&str]) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        let mut enabled_flags = HashSet::new();
+        let mut arguments = arguments.iter();
+        while let Some(argument) = arguments.next() {
+            if flags.contains(&argument.as_str()) {
+                enabled_flags.insert(argument.clone());
+            } else if options.contains(&argument.as_str()) {
+                let value = arguments
+                    .next()
+                    .ok_or_else(|| format!("missing value for `{argument}`"))?;
+                values.insert(argument.clone(), value.clone());
+            } else {
+                return Err(format!("unknown argument `{argument}`"));
+            }
+        }
+        Ok(Self {
+            values,
+            flags: enabled_flags,
+        })
+    }
+
+    pub fn value<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.values
+            .get(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid value `{value}` for `{name}`"))
+            })
+            .transpose()
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arguments::Arguments;
+
+    fn arguments(arguments: &[&str]) -> Vec<String> {
+        arguments.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn values_and_flags_are_parsed() {
+        let under_test = Arguments::parse(
+            &arguments(&["--starting-ease", "2.5", "--dry-run"]),
+            &["--starting-ease", "--deck"],
+            &["--dry-run", "--yes"],
+        )
+        .expect("arguments should be valid");
+
+        assert_eq!(under_test.value::<f64>("--starting-ease"), Ok(Some(2.5)));
+        assert_eq!(under_test.value::<String>("--deck"), Ok(None));
+        assert!(under_test.flag("--dry-run"));
+        assert!(!under_test.flag("--yes"));
+    }
+
+    #[test]
+    fn unknown_argument_is_rejected() {
+        let result = Arguments::parse(&arguments(&["--typo"]), &["--deck"], &[]);
+
+        assert_eq!(result.err(), Some("unknown argument `--typo`".to_string()));
+    }
+
+    #[test]
+    fn missing_value_is_rejected() {
+        let result = Arguments::parse(&arguments(&["--deck"]), &["--deck"], &[]);
+
+        assert_eq!(result.err(), Some("missing value for `--deck`".to_string()));
+    }
+
+    #[test]
+    fn invalid_value_is_rejected() {
+        let under_test = Arguments::parse(
+            &arguments(&["--starting-ease", "high"]),
+            &["--starting-ease"],
+            &[],
+        )
+        .expect("arguments should be valid");
+
+        assert_eq!(
+            under_test.value::<f64>("--starting-ease"),
+            Err("invalid value `high` for `--starting-ease`".to_string())
+        );
+    }
+}
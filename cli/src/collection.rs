@@ -0,0 +1,641 @@
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anki_srs_kai::Answer;
+use rusqlite::{Connection, OpenFlags};
+use serde_json::{Map, Value};
+use tempfile::TempDir;
+
+// Packages exported by newer Anki versions contain a zstd compressed
+// collection, and only keep `collection.anki2` for compatibility with older
+// versions, which is why the newest format is preferred
+//
+// See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/import_export/package/meta.rs
+const PACKAGE_COLLECTIONS: [(&str, bool); 3] = [
+    ("collection.anki21b", true),
+    ("collection.anki21", false),
+    ("collection.anki2", false),
+];
+
+#[derive(Debug)]
+pub enum CollectionError {
+    Io(std::io::Error),
+    Sqlite(rusqlite::Error),
+    Zip(zip::result::ZipError),
+    MissingCollection,
+    ReadOnlyPackage,
+    InvalidCardData { card_id: i64, reason: String },
+}
+
+impl Display for CollectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CollectionError::Io(e) => write!(f, "{e}"),
+            CollectionError::Sqlite(e) => write!(f, "failed to access the collection: {e}"),
+            CollectionError::Zip(e) => write!(f, "failed to unpack the package: {e}"),
+            CollectionError::MissingCollection => {
+                write!(f, "the package does not contain a collection")
+            }
+            CollectionError::ReadOnlyPackage => {
+                write!(
+                    f,
+                    "packages cannot be modified, use a collection.anki2 file"
+                )
+            }
+            CollectionError::InvalidCardData { card_id, reason } => {
+                write!(f, "card {card_id} has invalid data: {reason}")
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for CollectionError {
+    fn from(e: std::io::Error) -> Self {
+        CollectionError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for CollectionError {
+    fn from(e: rusqlite::Error) -> Self {
+        CollectionError::Sqlite(e)
+    }
+}
+
+impl From<zip::result::ZipError> for CollectionError {
+    fn from(e: zip::result::ZipError) -> Self {
+        CollectionError::Zip(e)
+    }
+}
+
+// See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/card/mod.rs#L40
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardType {
+    New,
+    Learn,
+    Review,
+    Relearn,
+}
+
+impl CardType {
+    fn from_code(code: i64) -> Option<Self> {
+        match code {
+            0 => Some(CardType::New),
+            1 => Some(CardType::Learn),
+            2 => Some(CardType::Review),
+            3 => Some(CardType::Relearn),
+            _ => None,
+        }
+    }
+}
+
+// See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/revlog/mod.rs#L65-L76
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RevlogKind {
+    Learning,
+    Review,
+    Relearning,
+    /// Old Anki versions called this "Cram" or "Early". It's assigned when
+    /// reviewing cards before they're due, or when rescheduling is disabled.
+    Filtered,
+    Manual,
+    Rescheduled,
+}
+
+impl RevlogKind {
+    fn from_code(code: i64) -> Option<Self> {
+        match code {
+            0 => Some(RevlogKind::Learning),
+            1 => Some(RevlogKind::Review),
+            2 => Some(RevlogKind::Relearning),
+            3 => Some(RevlogKind::Filtered),
+            4 => Some(RevlogKind::Manual),
+            5 => Some(RevlogKind::Rescheduled),
+            _ => None,
+        }
+    }
+}
+
+/// The `data` column of a card, with the custom data stored as a JSON string
+/// under `cd` already decoded.
+///
+/// See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/storage/card/data.rs#L60
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CardData {
+    fields: Map<String, Value>,
+    custom_data: Map<String, Value>,
+}
+
+impl CardData {
+    pub fn parse(data: &str) -> Result<Self, String> {
+        if data.is_empty() {
+            return Ok(Self::default());
+        }
+        let fields = serde_json::from_str::<Map<String, Value>>(data)
+            .map_err(|e| format!("`data` is not a JSON object: {e}"))?;
+        let custom_data = match fields.get("cd") {
+            None => Map::new(),
+            Some(Value::String(custom_data)) if custom_data.is_empty() => Map::new(),
+            Some(Value::String(custom_data)) => serde_json::from_str(custom_data)
+                .map_err(|e| format!("`cd` is not a JSON object: {e}"))?,
+            Some(value) => return Err(format!("`cd` is not a string: {value}")),
+        };
+        Ok(Self {
+            fields,
+            custom_data,
+        })
+    }
+
+    /// The number of consecutive successful reviews stored by the custom
+    /// scheduler.
+    pub fn consecutive_successful_reviews(&self) -> Option<u32> {
+        self.custom_data
+            .get("c")
+            .and_then(Value::as_u64)
+            .and_then(|c| u32::try_from(c).ok())
+    }
+
+    /// Set the number of consecutive successful reviews, or remove it from the
+    /// custom data if it is `None`, the same way the custom scheduler does.
+    pub fn set_consecutive_successful_reviews(&mut self, c: Option<u32>) {
+        match c {
+            Some(c) => self.custom_data.insert("c".to_string(), Value::from(c)),
+            None => self.custom_data.shift_remove("c"),
+        };
+    }
+}
+
+impl Display for CardData {
+    /// Encode the card data in the format of the `data` column.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut fields = self.fields.clone();
+        if self.custom_data.is_empty() {
+            fields.shift_remove("cd");
+        } else {
+            // Replacing the existing value keeps the order of the fields
+            fields.insert(
+                "cd".to_string(),
+                Value::String(Value::Object(self.custom_data.clone()).to_string()),
+            );
+        }
+        if fields.is_empty() {
+            Ok(())
+        } else {
+            write!(f, "{}", Value::Object(fields))
+        }
+    }
+}
+
+pub struct Card {
+    pub id: i64,
+    pub deck_id: i64,
+    pub card_type: CardType,
+    pub interval: i64,
+    /// The ease factor in permille, for example 2500 for 250%.
+    pub ease_factor: i64,
+    pub reps: i64,
+    pub lapses: i64,
+    pub data: CardData,
+    /// The `data` column exactly as it is stored in the collection.
+    pub raw_data: String,
+}
+
+pub struct RevlogEntry {
+    /// The epoch-milliseconds timestamp of the review.
+    pub id: i64,
+    pub card_id: i64,
+    /// review: 1 (again), 2 (hard), 3 (good), 4 (easy)
+    /// learn/relearn: 1 (again), 2 (good), 3 (easy)
+    /// 0 represents manual rescheduling
+    ///
+    /// See: https://github.com/ankitects/anki/blob/25.07.5/rslib/src/revlog/mod.rs#L43
+    pub ease: u8,
+    pub interval: i64,
+    pub last_interval: i64,
+    /// The ease factor in permille after the review.
+    pub ease_factor: i64,
+    pub kind: RevlogKind,
+}
+
+impl RevlogEntry {
+    /// The answer button of a review entry, or `None` for any other kind of
+    /// entry. Filtered entries count as reviews when a review card was
+    /// rescheduled by a filtered deck, just like the custom scheduler handles
+    /// them.
+    pub fn review_answer(&self) -> Option<Answer> {
+        let is_review = match self.kind {
+            RevlogKind::Review => true,
+            // The previous interval is only in days for review cards, and
+            // previewing a card without rescheduling it gives no interval
+            RevlogKind::Filtered => self.last_interval > 0 && self.interval != 0,
+            _ => false,
+        };
+        if !is_review {
+            return None;
+        }
+        match self.ease {
+            1 => Some(Answer::Again),
+            2 => Some(Answer::Hard),
+            3 => Some(Answer::Good),
+            4 => Some(Answer::Easy),
+            _ => None,
+        }
+    }
+}
+
+/// An Anki collection, either opened directly from a `collection.anki2` file or
+/// unpacked read-only from an `.apkg` or `.colpkg` package.
+pub struct Collection {
+    connection: Connection,
+    // Keeps the unpacked collection of a package until the collection is
+    // closed
+    _unpacked_directory: Option<TempDir>,
+}
+
+impl Collection {
+    /// Open the collection read-only.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, CollectionError> {
+        let path = path.as_ref();
+        if is_package(path) {
+            let unpacked_directory = TempDir::new()?;
+            let collection = unpack(path, unpacked_directory.path())?;
+            Ok(Self {
+                connection: open_read_only(&collection)?,
+                _unpacked_directory: Some(unpacked_directory),
+            })
+        } else {
+            Ok(Self {
+                connection: open_read_only(path)?,
+                _unpacked_directory: None,
+            })
+        }
+    }
+
+    /// Open a `collection.anki2` file for writing. Anki must be closed, since
+    /// the collection is locked while Anki is open.
+    pub fn open_writable<P: AsRef<Path>>(path: P) -> Result<Self, CollectionError> {
+        let path = path.as_ref();
+        Self::check_writable(path)?;
+        Ok(Self {
+            connection: Connection::open_with_flags(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?,
+            _unpacked_directory: None,
+        })
+    }
+
+    /// Check that the collection can be opened for writing, without opening
+    /// it. Packages are only unpacked read-only.
+    pub fn check_writable<P: AsRef<Path>>(path: P) -> Result<(), CollectionError> {
+        if is_package(path.as_ref()) {
+            return Err(CollectionError::ReadOnlyPackage);
+        }
+        Ok(())
+    }
+
+    pub fn cards(&self) -> Result<Vec<Card>, CollectionError> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, did, type, ivl, factor, reps, lapses, data FROM cards ORDER BY id",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, i64>(6)?,
+                row.get::<_, String>(7)?,
+            ))
+        })?;
+        let mut cards = Vec::new();
+        for row in rows {
+            let (id, deck_id, card_type, interval, ease_factor, reps, lapses, data) = row?;
+            let invalid_card_data = |reason| CollectionError::InvalidCardData {
+                card_id: id,
+                reason,
+            };
+            cards.push(Card {
+                id,
+                deck_id,
+                card_type: CardType::from_code(card_type)
+                    .ok_or_else(|| invalid_card_data(format!("unknown type {card_type}")))?,
+                interval,
+                ease_factor,
+                reps,
+                lapses,
+                data: CardData::parse(&data).map_err(invalid_card_data)?,
+                raw_data: data,
+            });
+        }
+        Ok(cards)
+    }
+
+    /// Every revlog entry sorted by the time of the review. Entries of an
+    /// unknown type are skipped.
+    pub fn revlog(&self) -> Result<Vec<RevlogEntry>, CollectionError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, cid, ease, ivl, lastIvl, factor, type FROM revlog ORDER BY id")?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, u8>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, i64>(6)?,
+            ))
+        })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, card_id, ease, interval, last_interval, ease_factor, kind) = row?;
+            if let Some(kind) = RevlogKind::from_code(kind) {
+                entries.push(RevlogEntry {
+                    id,
+                    card_id,
+                    ease,
+                    interval,
+                    last_interval,
+                    ease_factor,
+                    kind,
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Replace the `data` column of the cards in a single transaction, so that
+    /// either every card or no card is updated. Like Anki, the cards and the
+    /// collection are marked as modified, so that the next sync uploads the
+    /// changes instead of reverting them.
+    pub fn update_card_data(&mut self, card_data: &[(i64, String)]) -> Result<(), CollectionError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let transaction = self.connection.transaction()?;
+        {
+            // A usn of -1 marks the card as not synced yet, and `mod` is in
+            // seconds
+            let mut statement = transaction
+                .prepare("UPDATE cards SET data = ?2, mod = ?3, usn = -1 WHERE id = ?1")?;
+            for (card_id, data) in card_data {
+                statement.execute((card_id, data, now.as_secs() as i64))?;
+            }
+        }
+        // The `mod` of the collection is in milliseconds
+        transaction.execute("UPDATE col SET mod = ?1", [now.as_millis() as i64])?;
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+fn is_package(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("apkg") || extension.eq_ignore_ascii_case("colpkg")
+        })
+}
+
+fn open_read_only(path: &Path) -> Result<Connection, CollectionError> {
+    Ok(Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?)
+}
+
+/// Extract the collection of a package into the directory, and return the path
+/// of the extracted collection.
+fn unpack(package: &Path, directory: &Path) -> Result<std::path::PathBuf, CollectionError> {
+    let mut archive = zip::ZipArchive::new(File::open(package)?)?;
+    for (name, is_compressed) in PACKAGE_COLLECTIONS {
+        let mut file = match archive.by_name(name) {
+            Ok(file) => file,
+            Err(zip::result::ZipError::FileNotFound) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let collection = directory.join("collection.anki2");
+        let mut output = File::create(&collection)?;
+        if is_compressed {
+            zstd::stream::copy_decode(&mut file, &mut output)?;
+        } else {
+            std::io::copy(&mut file, &mut output)?;
+        }
+        return Ok(collection);
+    }
+    Err(CollectionError::MissingCollection)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    use anki_srs_kai::Answer;
+    use rusqlite::Connection;
+    use tempfile::TempDir;
+    use zip::write::SimpleFileOptions;
+
+    use crate::collection::{CardData, CardType, Collection, RevlogEntry, RevlogKind};
+
+    /// Create a collection with the subset of the Anki schema that is read by
+    /// the command line tool.
+    pub fn create_collection(
+        directory: &Path,
+        cards: &[(i64, i64, &str)],
+        revlog: &[(i64, i64, u8, i64)],
+    ) -> PathBuf {
+        let path = directory.join("collection.anki2");
+        let connection = Connection::open(&path).expect("collection should be created");
+        connection
+            .execute_batch(
+                "CREATE TABLE cards (
+                    id integer PRIMARY KEY,
+                    did integer NOT NULL,
+                    type integer NOT NULL,
+                    ivl integer NOT NULL,
+                    factor integer NOT NULL,
+                    reps integer NOT NULL,
+                    lapses integer NOT NULL,
+                    data text NOT NULL,
+                    mod integer NOT NULL,
+                    usn integer NOT NULL
+                );
+                CREATE TABLE revlog (
+                    id integer PRIMARY KEY,
+                    cid integer NOT NULL,
+                    ease integer NOT NULL,
+                    ivl integer NOT NULL,
+                    lastIvl integer NOT NULL,
+                    factor integer NOT NULL,
+                    type integer NOT NULL
+                );
+                CREATE TABLE col (
+                    id integer PRIMARY KEY,
+                    mod integer NOT NULL
+                );
+                INSERT INTO col VALUES (1, 0);",
+            )
+            .expect("schema should be created");
+        for (id, card_type, data) in cards {
+            connection
+                .execute(
+                    "INSERT INTO cards VALUES (?1, 1, ?2, 10, 2500, 5, 0, ?3, 0, 0)",
+                    (id, card_type, data),
+                )
+                .expect("card should be inserted");
+        }
+        for (id, card_id, ease, kind) in revlog {
+            connection
+                .execute(
+                    "INSERT INTO revlog VALUES (?1, ?2, ?3, 10, 5, 2500, ?4)",
+                    (id, card_id, ease, kind),
+                )
+                .expect("revlog entry should be inserted");
+        }
+        path
+    }
+
+    #[test]
+    fn custom_data_is_decoded_from_card_data() {
+        let result =
+            CardData::parse(r#"{"pos":9008,"cd":"{\"c\":3}"}"#).expect("card data should be valid");
+
+        assert_eq!(result.consecutive_successful_reviews(), Some(3));
+    }
+
+    #[test]
+    fn card_data_is_encoded_in_the_same_format() {
+        let data = r#"{"pos":9008,"cd":"{\"c\":3}"}"#;
+
+        let result = CardData::parse(data)
+            .expect("card data should be valid")
+            .to_string();
+
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn empty_card_data_has_no_custom_data() {
+        let result = CardData::parse("").expect("card data should be valid");
+
+        assert!(result.custom_data.is_empty());
+        assert_eq!(result.consecutive_successful_reviews(), None);
+    }
+
+    #[test]
+    fn invalid_custom_data_is_rejected() {
+        let result = CardData::parse(r#"{"cd":"{c:3}"}"#);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn cards_and_revlog_are_read_from_collection() {
+        let directory = TempDir::new().expect("temporary directory should be created");
+        let path = create_collection(
+            directory.path(),
+            &[(1, 2, r#"{"cd":"{\"c\":1}"}"#), (2, 0, "")],
+            &[(100, 1, 3, 1), (200, 1, 1, 2), (300, 1, 4, 1)],
+        );
+
+        let under_test = Collection::open(path).expect("collection should be opened");
+
+        let cards = under_test.cards().expect("cards should be read");
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].card_type, CardType::Review);
+        assert_eq!(cards[0].data.consecutive_successful_reviews(), Some(1));
+        assert_eq!(cards[1].card_type, CardType::New);
+        let revlog = under_test.revlog().expect("revlog should be read");
+        assert_eq!(
+            revlog
+                .iter()
+                .map(|entry| (entry.kind, entry.review_answer()))
+                .collect::<Vec<_>>(),
+            vec![
+                (RevlogKind::Review, Some(Answer::Good)),
+                (RevlogKind::Relearning, None),
+                (RevlogKind::Review, Some(Answer::Easy)),
+            ]
+        );
+    }
+
+    #[test]
+    fn collection_is_unpacked_from_package() {
+        let directory = TempDir::new().expect("temporary directory should be created");
+        let collection = create_collection(directory.path(), &[(1, 2, "")], &[]);
+        let package = directory.path().join("deck.colpkg");
+        let mut writer = zip::ZipWriter::new(
+            std::fs::File::create(&package).expect("package should be created"),
+        );
+        writer
+            .start_file("collection.anki21b", SimpleFileOptions::default())
+            .expect("file should be added to the package");
+        writer
+            .write_all(
+                &zstd::encode_all(
+                    std::fs::read(collection)
+                        .expect("collection should be read")
+                        .as_slice(),
+                    0,
+                )
+                .expect("collection should be compressed"),
+            )
+            .expect("collection should be written to the package");
+        writer.finish().expect("package should be written");
+
+        let under_test = Collection::open(package).expect("package should be opened");
+
+        let result = under_test.cards().expect("cards should be read");
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn updated_cards_and_collection_are_marked_as_modified() {
+        let directory = TempDir::new().expect("temporary directory should be created");
+        let path = create_collection(directory.path(), &[(1, 2, ""), (2, 2, "")], &[]);
+        let mut under_test = Collection::open_writable(&path).expect("collection should be opened");
+
+        under_test
+            .update_card_data(&[(1, r#"{"cd":"{\"c\":1}"}"#.to_string())])
+            .expect("card data should be updated");
+
+        let connection = Connection::open(&path).expect("collection should be opened");
+        let cards = connection
+            .prepare("SELECT mod > 0, usn FROM cards ORDER BY id")
+            .expect("statement should be prepared")
+            .query_map([], |row| {
+                Ok((row.get::<_, bool>(0)?, row.get::<_, i64>(1)?))
+            })
+            .expect("cards should be read")
+            .collect::<Result<Vec<(bool, i64)>, _>>()
+            .expect("cards should be read");
+        assert_eq!(cards, vec![(true, -1), (false, 0)]);
+        let collection_modified = connection
+            .query_row("SELECT mod > 0 FROM col", [], |row| row.get::<_, bool>(0))
+            .expect("collection should be read");
+        assert!(collection_modified);
+    }
+
+    #[test]
+    fn filtered_entries_only_count_when_a_review_card_is_rescheduled() {
+        let entry = |last_interval, interval| RevlogEntry {
+            id: 100,
+            card_id: 1,
+            ease: 3,
+            interval,
+            last_interval,
+            ease_factor: 2500,
+            kind: RevlogKind::Filtered,
+        };
+
+        assert_eq!(entry(5, 10).review_answer(), Some(Answer::Good));
+        assert_eq!(entry(5, 0).review_answer(), None);
+        assert_eq!(entry(-600, 10).review_answer(), None);
+    }
+}
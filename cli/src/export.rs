@@ -0,0 +1,72 @@
+use crate::arguments::Arguments;
+use crate::collection::Collection;
+
+const USAGE: &str = "\
+Usage: anki_srs_kai_cli export --collection <path> --table <cards|revlog>
+
+Print the cards or the review log of a collection as CSV, without modifying the
+collection.
+
+Options:
+    --collection <path>    A collection.anki2 file, or an .apkg or .colpkg
+                           package
+    --table <table>        Either `cards` or `revlog`";
+
+pub fn run(arguments: &[String]) -> Result<String, String> {
+    if arguments.iter().any(|argument| argument == "--help") {
+        return Ok(USAGE.to_string());
+    }
+    let arguments = Arguments::parse(arguments, &["--collection", "--table"], &[])?;
+    let path = arguments
+        .value::<String>("--collection")?
+        .ok_or_else(|| "missing `--collection`".to_string())?;
+    let table = arguments
+        .value::<String>("--table")?
+        .ok_or_else(|| "missing `--table`".to_string())?;
+
+    let collection = Collection::open(&path).map_err(|e| e.to_string())?;
+    let mut lines = Vec::new();
+    match table.as_str() {
+        "cards" => {
+            lines.push("id,deck_id,type,interval,ease_factor,reps,lapses,c".to_string());
+            for card in collection.cards().map_err(|e| e.to_string())? {
+                lines.push(format!(
+                    "{},{},{:?},{},{},{},{},{}",
+                    card.id,
+                    card.deck_id,
+                    card.card_type,
+                    card.interval,
+                    card.ease_factor,
+                    card.reps,
+                    card.lapses,
+                    card.data
+                        .consecutive_successful_reviews()
+                        .map(|c| c.to_string())
+                        .unwrap_or_default()
+                ));
+            }
+        }
+        "revlog" => {
+            lines
+                .push("id,card_id,type,ease,answer,interval,last_interval,ease_factor".to_string());
+            for entry in collection.revlog().map_err(|e| e.to_string())? {
+                lines.push(format!(
+                    "{},{},{:?},{},{},{},{},{}",
+                    entry.id,
+                    entry.card_id,
+                    entry.kind,
+                    entry.ease,
+                    entry
+                        .review_answer()
+                        .map(|answer| format!("{answer:?}"))
+                        .unwrap_or_default(),
+                    entry.interval,
+                    entry.last_interval,
+                    entry.ease_factor
+                ));
+            }
+        }
+        _ => return Err(format!("unknown table `{table}`")),
+    }
+    Ok(lines.join("\n"))
+}
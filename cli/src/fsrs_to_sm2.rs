@@ -0,0 +1,179 @@
+use anki_srs_kai::{Fsrs, PowerLawFit};
+
+use crate::arguments::Arguments;
+
+const USAGE: &str = "\
+Usage: anki_srs_kai_cli fsrs-to-sm2 [options]
+
+Options:
+    --parameters <w0,w1,...>      Comma separated FSRS-5 or FSRS-6 parameters
+                                  [default: the default FSRS-6 parameters]
+    --starting-ease <ease>        The Starting Ease of the deck [default: 2.5]
+    --desired-retention <ratio>   The desired retention [default: 0.9]
+    --deck <name>                 The deck name of the printed deckOptions entry
+                                  [default: Global Settings]";
+
+// FSRS-5 does not have the trainable decay of FSRS-6, nor the parameter for
+// same day reviews which is unused here
+const FSRS_5_DECAY: f64 = 0.5;
+
+pub fn run(arguments: &[String]) -> Result<String, String> {
+    if arguments.iter().any(|argument| argument == "--help") {
+        return Ok(USAGE.to_string());
+    }
+    let arguments = Arguments::parse(
+        arguments,
+        &[
+            "--parameters",
+            "--starting-ease",
+            "--desired-retention",
+            "--deck",
+        ],
+        &[],
+    )?;
+    let parameters = match arguments.value::<String>("--parameters")? {
+        Some(parameters) => parse_parameters(&parameters)?,
+        None => Fsrs::DEFAULT_PARAMETERS,
+    };
+    let starting_ease = arguments.value("--starting-ease")?.unwrap_or(2.5);
+    if !(1.3..=9.99).contains(&starting_ease) {
+        return Err(format!(
+            "`--starting-ease` {starting_ease} is not between 1.3 and 9.99"
+        ));
+    }
+    let desired_retention = arguments.value("--desired-retention")?.unwrap_or(0.9);
+    if !(desired_retention > 0.0 && desired_retention < 1.0) {
+        return Err(format!(
+            "`--desired-retention` {desired_retention} is not between 0 and 1"
+        ));
+    }
+    let deck_name = arguments
+        .value("--deck")?
+        .unwrap_or_else(|| "Global Settings".to_string());
+
+    let approximation =
+        anki_srs_kai::fsrs_to_sm2(&Fsrs::new(parameters), starting_ease, desired_retention);
+
+    let fit_quality = |name: &str, fit: &PowerLawFit| {
+        format!(
+            "{name}:\n    \
+             Mean Squared Error (MSE): {:.9}\n    \
+             Mean Absolute Error (MAE): {:.9}\n    \
+             Root Mean Squared Error (RMSE): {:.9}\n",
+            fit.mean_squared_error, fit.mean_absolute_error, fit.root_mean_squared_error
+        )
+    };
+    let multiplier = |name: &str, fit: &PowerLawFit| {
+        format!(
+            "            {name}: (currentEaseFactor, currentInterval) => {{\n                \
+             return currentEaseFactor * Math.pow(currentInterval, {:.9}) + ({:.9});\n            \
+             }},\n",
+            fit.a, fit.b
+        )
+    };
+    let parameters = parameters
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    Ok(format!(
+        "Lower error values are better. RMSE provides better insight in how well the
+multipliers fit to the FSRS stability increase than MSE and MAE.
+
+{}{}{}
+Set the graduating interval in the deck options to the one of the rating you
+most often press when first learning a new card. If you're not sure, use
+`Graduating interval (good)`.
+
+Graduating interval (again): {:.0}
+Graduating interval (hard): {:.0}
+Graduating interval (good): {:.0}
+Easy interval: {:.0}
+
+Add the following entry to `deckOptions` in the Custom scheduling field of the
+deck options:
+
+    {deck_name:?}: {{
+        easeReward: {{
+            minimumConsecutiveSuccessfulReviewsRequiredForReward: 3,
+            baseEaseReward: 0.05,
+            stepEaseReward: 0.05,
+            minimumEase: 1.30,
+            maximumEase: 2.50,
+        }},
+        scheduler: {{
+            enableFuzz: true,
+            maximumInterval: 36500,
+            intervalModifier: {:?},
+            // Approximation of the FSRS parameters
+            // [{parameters}]
+{}{}{}        }},
+    }},",
+        fit_quality("Hard", &approximation.hard),
+        fit_quality("Good", &approximation.good),
+        fit_quality("Easy", &approximation.easy),
+        approximation.graduating_interval_again,
+        approximation.graduating_interval_hard,
+        approximation.graduating_interval_good,
+        approximation.easy_interval,
+        approximation.interval_modifier,
+        multiplier("calculateHardMultiplier", &approximation.hard),
+        multiplier("calculateGoodMultiplier", &approximation.good),
+        multiplier("calculateEasyMultiplier", &approximation.easy),
+    ))
+}
+
+fn parse_parameters(parameters: &str) -> Result<[f64; 21], String> {
+    let values = parameters
+        .split(',')
+        .map(|parameter| {
+            parameter
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("invalid FSRS parameter `{}`", parameter.trim()))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    match values.len() {
+        19 => {
+            let mut parameters = [0.0; 21];
+            parameters[..19].copy_from_slice(&values);
+            parameters[20] = FSRS_5_DECAY;
+            Ok(parameters)
+        }
+        21 => Ok(values
+            .try_into()
+            .expect("21 values should fit in the parameters")),
+        n => Err(format!(
+            "expected 19 FSRS-5 or 21 FSRS-6 parameters, but got {n}"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fsrs_to_sm2::parse_parameters;
+
+    #[test]
+    fn fsrs_5_parameters_use_a_fixed_decay() {
+        let result = parse_parameters(
+            "0.40255, 1.18385, 3.173, 15.69105, 7.1949, 0.5345, 1.4604, 0.0046, 1.54575, \
+             0.1192, 1.01925, 1.9395, 0.11, 0.29605, 2.2698, 0.2315, 2.9898, 0.51655, 0.6621",
+        )
+        .expect("parameters should be valid");
+
+        assert_eq!(result[0], 0.40255);
+        assert_eq!(result[19], 0.0);
+        assert_eq!(result[20], 0.5);
+    }
+
+    #[test]
+    fn wrong_number_of_parameters_is_rejected() {
+        let result = parse_parameters("0.212, 1.2931");
+
+        assert_eq!(
+            result,
+            Err("expected 19 FSRS-5 or 21 FSRS-6 parameters, but got 2".to_string())
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use std::process::ExitCode;
+
+mod arguments;
+mod collection;
+mod export;
+mod fsrs_to_sm2;
+mod update_custom_data;
+
+const USAGE: &str = "\
+Usage: anki_srs_kai_cli <command> [options]
+
+Commands:
+    export         Print the cards or the review log of a collection as CSV
+    fsrs-to-sm2    Approximate FSRS parameters with the multipliers of the
+                   custom scheduler
+    update-custom-data
+                   Recompute the consecutive successful reviews of every card
+
+Run `anki_srs_kai_cli <command> --help` for the options of a command.";
+
+fn main() -> ExitCode {
+    let arguments = std::env::args().skip(1).collect::<Vec<String>>();
+    let result = match arguments.split_first() {
+        Some((command, arguments)) if command == "export" => export::run(arguments),
+        Some((command, arguments)) if command == "fsrs-to-sm2" => fsrs_to_sm2::run(arguments),
+        Some((command, arguments)) if command == "update-custom-data" => {
+            update_custom_data::run(arguments)
+        }
+        Some((command, _)) if command == "--help" => Ok(USAGE.to_string()),
+        Some((command, _)) => Err(format!("unknown command `{command}`\n\n{USAGE}")),
+        None => Err(format!("missing command\n\n{USAGE}")),
+    };
+    match result {
+        Ok(output) => {
+            println!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anki_srs_kai::{next_consecutive_successful_reviews, HardBehaviour, StreakCredit};
+use serde_json::{Map, Value};
+
+use crate::arguments::Arguments;
+use crate::collection::{Card, CardType, Collection, RevlogEntry};
+
+const USAGE: &str = "\
+Usage: anki_srs_kai_cli update-custom-data --collection <path> [options]
+
+Recompute the number of consecutive successful reviews of every review card
+from its review log, the same way the custom scheduler counts them. The `data`
+column of the changed cards is backed up before the collection is modified.
+Close Anki before running this command.
+
+Options:
+    --collection <path>    The collection.anki2 file of the Anki profile
+    --dry-run              Only print the changes without modifying the
+                           collection
+    --yes                  Modify the collection without asking for
+                           confirmation
+    --restore <backup>     Restore the `data` column from a backup made by this
+                           command
+
+Options that must match `easeReward` of the deck options:
+    --hard-behaviour <preserve|reset|decrement|countAsSuccess>
+                           How pressing Hard affects the streak, `preserve` by
+                           default
+    --minimum-interval <days>
+                           Successful reviews of cards with a shorter interval
+                           earn no credit
+    --overdue-credit <credit>
+                           Additional credit for each multiple of the interval
+                           that a card is overdue";
+
+const MILLISECONDS_PER_DAY: f64 = 86_400_000.0;
+
+/// The number of consecutive successful reviews of a card that changes.
+#[derive(Debug, PartialEq)]
+struct Change {
+    card_id: i64,
+    old: Option<u32>,
+    new: Option<u32>,
+    old_data: String,
+    new_data: String,
+}
+
+pub fn run(arguments: &[String]) -> Result<String, String> {
+    if arguments.iter().any(|argument| argument == "--help") {
+        return Ok(USAGE.to_string());
+    }
+    let arguments = Arguments::parse(
+        arguments,
+        &[
+            "--collection",
+            "--restore",
+            "--minimum-interval",
+            "--overdue-credit",
+            "--hard-behaviour",
+        ],
+        &["--dry-run", "--yes"],
+    )?;
+    let path = arguments
+        .value::<PathBuf>("--collection")?
+        .ok_or_else(|| "missing `--collection`".to_string())?;
+    // Packages are rejected before anything is printed or backed up
+    Collection::check_writable(&path).map_err(|e| e.to_string())?;
+
+    if let Some(backup) = arguments.value::<PathBuf>("--restore")? {
+        if !arguments.flag("--yes")
+            && !confirm(&format!(
+                "Restore the card data of `{}` from `{}`?",
+                path.display(),
+                backup.display()
+            ))?
+        {
+            return Ok("The collection was not modified.".to_string());
+        }
+        let restored = restore(&path, &backup)?;
+        return Ok(format!("Restored the data of {restored} cards."));
+    }
+
+    let minimum_interval = arguments.value::<u32>("--minimum-interval")?;
+    let overdue_credit = arguments.value::<f64>("--overdue-credit")?;
+    if overdue_credit
+        .is_some_and(|overdue_credit| !overdue_credit.is_finite() || overdue_credit < 0.0)
+    {
+        return Err("`--overdue-credit` must be a positive number".to_string());
+    }
+    let streak_credit = (minimum_interval.is_some() || overdue_credit.is_some())
+        .then(|| StreakCredit::new(minimum_interval.unwrap_or(0), overdue_credit.unwrap_or(0.0)));
+
+    let hard_behaviour = match arguments.value::<String>("--hard-behaviour")?.as_deref() {
+        None | Some("preserve") => HardBehaviour::Preserve,
+        Some("reset") => HardBehaviour::Reset,
+        Some("decrement") => HardBehaviour::Decrement,
+        Some("countAsSuccess") => HardBehaviour::CountAsSuccess,
+        Some(hard_behaviour) => {
+            return Err(format!(
+                "`--hard-behaviour` must be one of preserve, reset, decrement, or \
+                 countAsSuccess, but was `{hard_behaviour}`"
+            ))
+        }
+    };
+
+    let changes = {
+        let collection = Collection::open(&path).map_err(|e| e.to_string())?;
+        let cards = collection.cards().map_err(|e| e.to_string())?;
+        let revlog = collection.revlog().map_err(|e| e.to_string())?;
+        compute_changes(&cards, &revlog, streak_credit, hard_behaviour)
+    };
+    if changes.is_empty() {
+        return Ok("Every card is already up to date.".to_string());
+    }
+
+    let format_c = |c: Option<u32>| c.map_or_else(|| "none".to_string(), |c| c.to_string());
+    for change in &changes {
+        println!(
+            "card {}: c {} -> {}",
+            change.card_id,
+            format_c(change.old),
+            format_c(change.new)
+        );
+    }
+    if arguments.flag("--dry-run") {
+        return Ok(format!(
+            "{} cards would be updated. The collection was not modified.",
+            changes.len()
+        ));
+    }
+    if !arguments.flag("--yes") && !confirm(&format!("Update {} cards?", changes.len()))? {
+        return Ok("The collection was not modified.".to_string());
+    }
+
+    let backup = write_changes(&path, &changes)?;
+    Ok(format!(
+        "Updated {} cards. The previous card data was backed up to `{}`, which can be \
+         restored with `--restore`.",
+        changes.len(),
+        backup.display()
+    ))
+}
+
+/// Count the consecutive successful reviews the same way as the custom
+/// scheduler. Only reviews of cards in the review state are considered, since
+/// the custom scheduler does not change any other card.
+fn consecutive_successful_reviews(
+    revlog: &[&RevlogEntry],
+    streak_credit: Option<StreakCredit>,
+    hard_behaviour: HardBehaviour,
+) -> Option<u32> {
+    let mut c = None;
+    let mut last_review = None::<&RevlogEntry>;
+    for entry in revlog {
+        // Manual rescheduling does not review the card
+        if entry.ease == 0 {
+            continue;
+        }
+        let previous_review = last_review.replace(entry);
+        let Some(answer) = entry.review_answer() else {
+            continue;
+        };
+        // The revlog does not store the elapsed days, so they are approximated
+        // from the time since the previous review
+        let elapsed_days = previous_review.map_or(0, |previous_review| {
+            ((entry.id - previous_review.id) as f64 / MILLISECONDS_PER_DAY).round() as u32
+        });
+        let scheduled_days = u32::try_from(entry.last_interval).unwrap_or(0);
+        let credit = streak_credit.map_or(1, |streak_credit| {
+            streak_credit.credit(scheduled_days, elapsed_days)
+        });
+        c = next_consecutive_successful_reviews(answer, c, credit, hard_behaviour);
+    }
+    c
+}
+
+fn compute_changes(
+    cards: &[Card],
+    revlog: &[RevlogEntry],
+    streak_credit: Option<StreakCredit>,
+    hard_behaviour: HardBehaviour,
+) -> Vec<Change> {
+    let mut revlog_by_card = HashMap::<i64, Vec<&RevlogEntry>>::new();
+    for entry in revlog {
+        revlog_by_card.entry(entry.card_id).or_default().push(entry);
+    }
+
+    cards
+        .iter()
+        // Only review cards can have had a successful review, which also
+        // avoids adding unnecessary custom data to every other card
+        .filter(|card| card.card_type == CardType::Review)
+        .filter_map(|card| {
+            let old = card.data.consecutive_successful_reviews();
+            let new = consecutive_successful_reviews(
+                revlog_by_card.get(&card.id).map_or(&[], Vec::as_slice),
+                streak_credit,
+                hard_behaviour,
+            );
+            if old == new {
+                return None;
+            }
+            let mut data = card.data.clone();
+            data.set_consecutive_successful_reviews(new);
+            Some(Change {
+                card_id: card.id,
+                old,
+                new,
+                old_data: card.raw_data.clone(),
+                new_data: data.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn confirm(question: &str) -> Result<bool, String> {
+    print!("{question} [y/N] ");
+    std::io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| e.to_string())?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Back up the `data` column of the changed cards next to the collection, and
+/// then apply the changes. Return the path of the backup. Nothing is backed up
+/// when the collection cannot be opened for writing.
+fn write_changes(path: &Path, changes: &[Change]) -> Result<PathBuf, String> {
+    let mut collection = Collection::open_writable(path).map_err(|e| e.to_string())?;
+    let backup = Map::from_iter(changes.iter().map(|change| {
+        (
+            change.card_id.to_string(),
+            Value::String(change.old_data.clone()),
+        )
+    }));
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+    let mut backup_path = path.as_os_str().to_os_string();
+    backup_path.push(format!(".card-data-backup-{timestamp}.json"));
+    let backup_path = PathBuf::from(backup_path);
+    std::fs::write(&backup_path, Value::Object(backup).to_string())
+        .map_err(|e| format!("failed to write `{}`: {e}", backup_path.display()))?;
+
+    collection
+        .update_card_data(
+            &changes
+                .iter()
+                .map(|change| (change.card_id, change.new_data.clone()))
+                .collect::<Vec<(i64, String)>>(),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(backup_path)
+}
+
+/// Restore the `data` column of the cards in the backup, and return the number
+/// of restored cards.
+fn restore(path: &Path, backup: &Path) -> Result<usize, String> {
+    let content = std::fs::read_to_string(backup)
+        .map_err(|e| format!("failed to read `{}`: {e}", backup.display()))?;
+    let invalid_backup = || format!("`{}` is not a card data backup", backup.display());
+    let backup =
+        serde_json::from_str::<Map<String, Value>>(&content).map_err(|_| invalid_backup())?;
+    let card_data = backup
+        .into_iter()
+        .map(|(card_id, data)| match (card_id.parse::<i64>(), data) {
+            (Ok(card_id), Value::String(data)) => Ok((card_id, data)),
+            _ => Err(invalid_backup()),
+        })
+        .collect::<Result<Vec<(i64, String)>, String>>()?;
+
+    let mut collection = Collection::open_writable(path).map_err(|e| e.to_string())?;
+    collection
+        .update_card_data(&card_data)
+        .map_err(|e| e.to_string())?;
+    Ok(card_data.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use crate::collection::tests::create_collection;
+    use crate::collection::Collection;
+    use crate::update_custom_data::{compute_changes, restore, write_changes, Change};
+    use anki_srs_kai::{HardBehaviour, StreakCredit};
+
+    // ease: 1 (again), 2 (hard), 3 (good), 4 (easy)
+    // type: 1 (review), 2 (relearning), 3 (filtered)
+    #[test]
+    fn consecutive_successful_reviews_are_counted_after_the_last_again() {
+        let directory = TempDir::new().expect("temporary directory should be created");
+        let path = create_collection(
+            directory.path(),
+            &[(1, 2, r#"{"pos":1,"cd":"{\"c\":9}"}"#)],
+            &[
+                (100, 1, 3, 1),
+                (200, 1, 1, 1),
+                (300, 1, 3, 2),
+                (400, 1, 3, 1),
+                (500, 1, 2, 1),
+                (600, 1, 4, 3),
+                (700, 1, 4, 1),
+            ],
+        );
+        let collection = Collection::open(path).expect("collection should be opened");
+
+        let result = compute_changes(
+            &collection.cards().expect("cards should be read"),
+            &collection.revlog().expect("revlog should be read"),
+            None,
+            HardBehaviour::Preserve,
+        );
+
+        assert_eq!(
+            result,
+            vec![Change {
+                card_id: 1,
+                old: Some(9),
+                new: Some(3),
+                old_data: r#"{"pos":1,"cd":"{\"c\":9}"}"#.to_string(),
+                new_data: r#"{"pos":1,"cd":"{\"c\":3}"}"#.to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn rescheduled_reviews_in_filtered_decks_are_counted() {
+        let directory = TempDir::new().expect("temporary directory should be created");
+        let path = create_collection(
+            directory.path(),
+            &[(1, 2, "")],
+            &[(100, 1, 3, 1), (200, 1, 3, 3)],
+        );
+        let collection = Collection::open(path).expect("collection should be opened");
+
+        let result = compute_changes(
+            &collection.cards().expect("cards should be read"),
+            &collection.revlog().expect("revlog should be read"),
+            None,
+            HardBehaviour::Preserve,
+        );
+
+        assert_eq!(result[0].new, Some(2));
+    }
+
+    #[test]
+    fn count_is_removed_when_again_was_pressed_last() {
+        let directory = TempDir::new().expect("temporary directory should be created");
+        let path = create_collection(
+            directory.path(),
+            &[(1, 2, r#"{"cd":"{\"c\":3}"}"#)],
+            &[(100, 1, 3, 1), (200, 1, 1, 1)],
+        );
+        let collection = Collection::open(path).expect("collection should be opened");
+
+        let result = compute_changes(
+            &collection.cards().expect("cards should be read"),
+            &collection.revlog().expect("revlog should be read"),
+            None,
+            HardBehaviour::Preserve,
+        );
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].new, None);
+        assert_eq!(result[0].new_data, "");
+    }
+
+    #[test]
+    fn cards_that_are_not_in_review_are_unchanged() {
+        let directory = TempDir::new().expect("temporary directory should be created");
+        let path = create_collection(directory.path(), &[(1, 3, "")], &[(100, 1, 3, 1)]);
+        let collection = Collection::open(path).expect("collection should be opened");
+
+        let result = compute_changes(
+            &collection.cards().expect("cards should be read"),
+            &collection.revlog().expect("revlog should be read"),
+            None,
+            HardBehaviour::Preserve,
+        );
+
+        assert_eq!(result, vec![]);
+    }
+
+    #[test]
+    fn streak_credit_depends_on_interval_and_days_overdue() {
+        const DAY: i64 = 86_400_000;
+        let directory = TempDir::new().expect("temporary directory should be created");
+        // Every review entry has a previous interval of 5 days
+        let path = create_collection(
+            directory.path(),
+            &[(1, 2, ""), (2, 2, "")],
+            &[
+                (DAY, 1, 3, 1),
+                (6 * DAY, 1, 3, 1),
+                (21 * DAY, 1, 4, 1),
+                (DAY + 1, 2, 3, 1),
+            ],
+        );
+        let collection = Collection::open(path).expect("collection should be opened");
+        let cards = collection.cards().expect("cards should be read");
+        let revlog = collection.revlog().expect("revlog should be read");
+
+        let overdue = compute_changes(
+            &cards,
+            &revlog,
+            Some(StreakCredit::new(3, 1.0)),
+            HardBehaviour::Preserve,
+        );
+        let short_interval = compute_changes(
+            &cards,
+            &revlog,
+            Some(StreakCredit::new(7, 1.0)),
+            HardBehaviour::Preserve,
+        );
+
+        // 1 for the first review, 1 for the review on time, and 1 + 2 for the
+        // review 10 days overdue
+        assert_eq!(
+            overdue.iter().map(|change| change.new).collect::<Vec<_>>(),
+            vec![Some(5), Some(1)]
+        );
+        assert_eq!(short_interval, vec![]);
+    }
+
+    #[test]
+    fn hard_behaviour_is_honoured() {
+        let directory = TempDir::new().expect("temporary directory should be created");
+        let path = create_collection(
+            directory.path(),
+            &[(1, 2, "")],
+            &[
+                (100, 1, 3, 1),
+                (200, 1, 3, 1),
+                (300, 1, 2, 1),
+                (400, 1, 3, 1),
+            ],
+        );
+        let collection = Collection::open(path).expect("collection should be opened");
+        let cards = collection.cards().expect("cards should be read");
+        let revlog = collection.revlog().expect("revlog should be read");
+
+        let result = [
+            HardBehaviour::Preserve,
+            HardBehaviour::Reset,
+            HardBehaviour::Decrement,
+            HardBehaviour::CountAsSuccess,
+        ]
+        .map(|hard_behaviour| compute_changes(&cards, &revlog, None, hard_behaviour)[0].new);
+
+        assert_eq!(result, [Some(3), Some(1), Some(2), Some(4)]);
+    }
+
+    #[test]
+    fn changes_are_backed_up_and_can_be_restored() {
+        let directory = TempDir::new().expect("temporary directory should be created");
+        let path = create_collection(directory.path(), &[(1, 2, "")], &[(100, 1, 3, 1)]);
+        let read_data = || {
+            Collection::open(&path)
+                .expect("collection should be opened")
+                .cards()
+                .expect("cards should be read")[0]
+                .raw_data
+                .clone()
+        };
+        let changes = {
+            let collection = Collection::open(&path).expect("collection should be opened");
+            compute_changes(
+                &collection.cards().expect("cards should be read"),
+                &collection.revlog().expect("revlog should be read"),
+                None,
+                HardBehaviour::Preserve,
+            )
+        };
+
+        let backup = write_changes(&path, &changes).expect("changes should be written");
+        assert_eq!(read_data(), r#"{"cd":"{\"c\":1}"}"#);
+
+        let result = restore(&path, &backup).expect("backup should be restored");
+        assert_eq!(result, 1);
+        assert_eq!(read_data(), "");
+    }
+
+    #[test]
+    fn packages_are_rejected_before_backing_up() {
+        let directory = TempDir::new().expect("temporary directory should be created");
+        let path = create_collection(directory.path(), &[(1, 2, "")], &[(100, 1, 3, 1)]);
+        let changes = {
+            let collection = Collection::open(&path).expect("collection should be opened");
+            compute_changes(
+                &collection.cards().expect("cards should be read"),
+                &collection.revlog().expect("revlog should be read"),
+                None,
+                HardBehaviour::Preserve,
+            )
+        };
+
+        let result = write_changes(&directory.path().join("deck.apkg"), &changes);
+
+        assert_eq!(
+            result,
+            Err("packages cannot be modified, use a collection.anki2 file".to_string())
+        );
+        assert_eq!(
+            std::fs::read_dir(directory.path())
+                .expect("directory should be read")
+                .count(),
+            1
+        );
+    }
+}
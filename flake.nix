@@ -51,11 +51,11 @@
             buildPhaseCargoCommand = ''
               cargo check --profile release --frozen --all-targets
               cargo build --profile release --frozen --workspace --exclude wasm
-              cargo build --profile release --frozen --target ${targetSystem} --workspace --exclude xtask
+              cargo build --profile release --frozen --target ${targetSystem} --workspace --exclude xtask --exclude anki_srs_kai_cli
             '';
             checkPhaseCargoCommand = ''
               cargo test --profile release --frozen --workspace --exclude wasm --no-run
-              cargo test --profile release --frozen --target ${targetSystem} --workspace --exclude xtask --no-run
+              cargo test --profile release --frozen --target ${targetSystem} --workspace --exclude xtask --exclude anki_srs_kai_cli --no-run
             '';
           }
         );
@@ -65,7 +65,7 @@
           // {
             inherit cargoArtifacts;
 
-            cargoExtraArgs = "--frozen --target ${targetSystem} --workspace --exclude xtask";
+            cargoExtraArgs = "--frozen --target ${targetSystem} --workspace --exclude xtask --exclude anki_srs_kai_cli";
 
             nativeBuildInputs = with pkgs; [
               binaryen
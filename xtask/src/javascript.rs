@@ -53,6 +53,12 @@ const deckOptions = {
         },
     },
 };
+
+const schedulingOptions = {
+    // Set to "warn" to log invalid deck options to the console instead of
+    // showing an error
+    validation: "strict",
+};
 "#;
 
 pub const FOOTER: &str = r##"
@@ -68,7 +74,11 @@ const response = new Response(
 try {
     await wasm_bindgen(response);
 
-    wasm_bindgen.calculateNextCardStates(deckOptions, states);
+    wasm_bindgen.calculateNextCardStates(
+        deckOptions,
+        states,
+        typeof schedulingOptions === 'undefined' ? undefined : schedulingOptions,
+    );
 } catch (e) {
     function openCustomModal(message) {
         const modalContainer = document.createElement('div');